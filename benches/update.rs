@@ -0,0 +1,63 @@
+// Measures how the cost of a simulation tick scales with the number of entities.
+//
+// Usage:
+//   cargo bench --bench update
+
+#[macro_use]
+extern crate criterion;
+extern crate contagion;
+
+use criterion::{BatchSize, Criterion};
+
+use contagion::simulation::initial_state::initial_state;
+use contagion::simulation::scenario::Scenario;
+use contagion::simulation::spatial_grid::SpatialGrid;
+use contagion::simulation::state::*;
+use contagion::simulation::timestep::TIMESTEP;
+use contagion::simulation::update::{update, UpdateArgs};
+
+const ENTITY_COUNTS: [u32; 5] = [100, 500, 1000, 2000, 5000];
+
+const SEED: u32 = 0;
+
+// Let the match develop for a second before measuring, so the cops and zombies have
+// had time to find targets
+const WARM_UP_TICKS: usize = 60;
+
+fn warmed_up_state(entity_count: u32) -> State {
+    let mut state = initial_state(&Scenario::generated(entity_count), SEED);
+    let args = UpdateArgs { dt: TIMESTEP };
+    for _ in 0..WARM_UP_TICKS {
+        update(&args, &mut state);
+    }
+    state
+}
+
+fn bench_update(c: &mut Criterion) {
+    c.bench_function_over_inputs("update", |b, &&entity_count| {
+        let state = warmed_up_state(entity_count);
+        let args = UpdateArgs { dt: TIMESTEP };
+        b.iter_batched(
+            || state.clone(),
+            |mut state| update(&args, &mut state),
+            BatchSize::LargeInput);
+    }, &ENTITY_COUNTS);
+}
+
+fn bench_collision_queries(c: &mut Criterion) {
+    c.bench_function_over_inputs("collision_queries", |b, &&entity_count| {
+        let state = warmed_up_state(entity_count);
+        b.iter(|| {
+            // Build the grid and find every entity's potential collisions, as update does
+            let grid = SpatialGrid::from_entities(&state.entities);
+            let mut candidates = 0;
+            for entity in &state.entities {
+                candidates += grid.query(entity.position, 2.0 * ENTITY_RADIUS).count();
+            }
+            candidates
+        });
+    }, &ENTITY_COUNTS);
+}
+
+criterion_group!(benches, bench_update, bench_collision_queries);
+criterion_main!(benches);
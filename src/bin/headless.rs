@@ -0,0 +1,136 @@
+// Runs the simulation without a window, renderer or audio, for batch experiments.
+//
+// Usage:
+//   headless [--seed N] [--entities N] [--layout grid|city] [--scenario PATH] [--tick-rate HZ] [--max-duration SECONDS]
+
+extern crate contagion;
+
+use std::path::Path;
+use std::str::FromStr;
+
+use contagion::core::scalar::Scalar;
+use contagion::simulation::initial_state::initial_state;
+use contagion::simulation::outcome::*;
+use contagion::simulation::scenario::Scenario;
+use contagion::simulation::statistics::score;
+use contagion::simulation::timestep::TICK_RATE;
+use contagion::simulation::update::{update, UpdateArgs};
+
+struct Options {
+    seed: u32,
+    entity_count: u32,
+    // Generated layout, either the grid of square buildings or a procedural city
+    layout: String,
+    // Scenario file to play instead of a generated layout
+    scenario_path: Option<String>,
+    tick_rate: Scalar,
+    max_duration: Scalar,
+}
+
+const USAGE: &str = "usage: headless [--seed N] [--entities N] [--layout grid|city] [--scenario PATH] [--tick-rate HZ] [--max-duration SECONDS]";
+
+fn parse_value<T: FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value for {}: {}", flag, value))
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        seed: 0,
+        entity_count: 100,
+        layout: "grid".to_string(),
+        scenario_path: None,
+        tick_rate: TICK_RATE,
+        max_duration: 600.0,
+    };
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut i = 0;
+    while i < args.len() {
+        let flag = &args[i];
+        let value = match args.get(i + 1) {
+            Some(value) => value,
+            None => return Err(format!("missing value for {}", flag)),
+        };
+
+        match flag.as_str() {
+            "--seed" => options.seed = parse_value(flag, value)?,
+            "--entities" => options.entity_count = parse_value(flag, value)?,
+            "--layout" => options.layout = value.clone(),
+            "--scenario" => options.scenario_path = Some(value.clone()),
+            "--tick-rate" => options.tick_rate = parse_value(flag, value)?,
+            "--max-duration" => options.max_duration = parse_value(flag, value)?,
+            _ => return Err(format!("unknown argument: {}", flag)),
+        }
+        i += 2;
+    }
+
+    if options.layout != "grid" && options.layout != "city" {
+        return Err(format!("unknown layout: {}", options.layout));
+    }
+
+    if options.tick_rate <= 0.0 {
+        return Err("tick rate must be positive".to_string());
+    }
+
+    Ok(options)
+}
+
+fn main() {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}", err);
+            eprintln!("{}", USAGE);
+            std::process::exit(1);
+        }
+    };
+
+    let scenario = match &options.scenario_path {
+        Some(path) => match Scenario::load_from_file(Path::new(path)) {
+            Ok(scenario) => scenario,
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        },
+        None if options.layout == "city" => Scenario::generated_city(options.entity_count, options.seed),
+        None => Scenario::generated(options.entity_count),
+    };
+
+    let mut state = initial_state(&scenario, options.seed);
+    let args = UpdateArgs { dt: 1.0 / options.tick_rate };
+    let max_ticks = (options.max_duration * options.tick_rate).ceil() as u64;
+
+    let mut ticks = 0;
+    let mut outcome = check_outcome(&state);
+    while outcome.is_none() && ticks < max_ticks {
+        update(&args, &mut state);
+        ticks += 1;
+        outcome = check_outcome(&state);
+    }
+
+    let elapsed = ticks as Scalar * args.dt;
+    if outcome == Some(Outcome::HumansWin) {
+        state.statistics.time_to_victory = Some(state.time);
+    }
+    let census = census(&state);
+    let statistics = &state.statistics;
+
+    match outcome {
+        Some(Outcome::HumansWin) => println!("Humans win after {:.2} seconds", elapsed),
+        Some(Outcome::ZombiesWin) => println!("Zombies win after {:.2} seconds", elapsed),
+        None => println!("No winner after {:.2} seconds", elapsed),
+    }
+    println!("  Ticks:     {}", ticks);
+    println!("  Cops:      {}", census.cops);
+    println!("  Civilians: {}", census.civilians);
+    println!("  Zombies:   {}", census.zombies);
+    println!("  Dead:      {}", census.dead);
+    println!("  Infected:  {}", census.infected);
+    println!("  Sheltered: {}", census.sheltered);
+    println!("  Shots:     {}", statistics.shots_fired);
+    println!("  Hits:      {}", statistics.hits);
+    println!("  Killed:    {}", statistics.total_zombies_killed());
+    println!("  Lost:      {}", statistics.civilians_lost);
+    println!("  Score:     {}", score(&state).total());
+}
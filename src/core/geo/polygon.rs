@@ -1,9 +1,10 @@
 use crate::core::vector::*;
 use crate::core::scalar::*;
+use serde::{Serialize, Deserialize};
 
 // Could probably refactor this to be a Vec<Edge>
 // Polygon is an ordered vec of vertices (represented by Vector2)
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Polygon(pub Vec<Vector2>);
 
 impl Polygon {
@@ -15,106 +16,151 @@ impl Polygon {
         self.0[i]
     }
 
-    // Find the normals of all edges of the polygon
+    // Find the outward normals of all edges of the polygon
     pub fn normals(&self) -> Vec<Vector2> {
         let mut out = Vec::new();
 
+        // The outside is to the right of each edge when the vertices go anticlockwise.
+        // Checking against the neighbouring vertex instead goes wrong at concave corners
+        let orientation = if self.signed_area() < 0.0 { -1.0 } else { 1.0 };
+
         for i in 0..self.num_sides() {
             let ab = self.0[(i + 1) % self.num_sides()] - self.0[i];
-            let ac = self.0[if i < 1 { self.num_sides()-1 } else { i-1 }] - self.0[i];
             let n = Vector2 { x: ab.y, y: -ab.x }.normalize();
-            let d = n.dot(ac);
+            out.push(orientation * n);
+        }
 
-            if d > 0.0 {
-                out.push(-n);
-            }
-            else {
-                out.push(n);
+        out
+    }
+
+    // Push every corner out along the normals of the sides meeting there, giving a
+    // slightly larger polygon around this one
+    pub fn outline(&self, distance: Scalar) -> Polygon {
+        let normals = self.normals();
+        let sides = self.num_sides();
+
+        Polygon((0..sides)
+            .map(|i| self.get(i) + distance * (normals[i] + normals[(i + sides - 1) % sides]))
+            .collect())
+    }
+
+    // Area of the polygon, positive if the vertices go anticlockwise and negative if clockwise
+    pub fn signed_area(&self) -> Scalar {
+        let mut twice_area = 0.0;
+        for i in 0..self.num_sides() {
+            twice_area += self.get(i).cross(self.get((i + 1) % self.num_sides()));
+        }
+        twice_area / 2.0
+    }
+
+    // Split the polygon into triangles by clipping off one corner at a time.
+    // Works for any simple polygon, convex or not
+    pub fn triangulate(&self) -> Vec<[Vector2; 3]> {
+        let orientation = if self.signed_area() < 0.0 { -1.0 } else { 1.0 };
+        let mut remaining: Vec<usize> = (0..self.num_sides()).collect();
+        let mut triangles = vec!();
+
+        while remaining.len() > 3 {
+            let count = remaining.len();
+            let corner = |i: usize| (
+                self.get(remaining[(i + count - 1) % count]),
+                self.get(remaining[i]),
+                self.get(remaining[(i + 1) % count]));
+
+            // A corner can be clipped if it is convex and no other vertex lies inside it
+            let ear = (0..count).find(|&i| {
+                let (a, b, c) = corner(i);
+                if (b - a).cross(c - b) * orientation <= 0.0 {
+                    return false;
+                }
+                !remaining.iter()
+                    .map(|&j| self.get(j))
+                    .filter(|&p| p != a && p != b && p != c)
+                    .any(|p| (b - a).cross(p - a) * orientation >= 0.0 &&
+                        (c - b).cross(p - b) * orientation >= 0.0 &&
+                        (a - c).cross(p - c) * orientation >= 0.0)
+            });
+
+            match ear {
+                Some(i) => {
+                    let (a, b, c) = corner(i);
+                    triangles.push([a, b, c]);
+                    remaining.remove(i);
+                }
+                // Only happens if the polygon crosses itself
+                None => break,
             }
         }
 
-        out
+        if remaining.len() == 3 {
+            triangles.push([self.get(remaining[0]), self.get(remaining[1]), self.get(remaining[2])]);
+        }
+
+        triangles
     }
 
     // Find the number of intersections with the line spanned by start and end
     pub fn num_intersects(&self, start: Vector2, end: Vector2) -> usize {
-        self.intersects(start, end).len()
+        (0..self.num_sides())
+            .filter(|&i| self.side_intersect_time(i, start, end).is_some())
+            .count()
     }
 
     // Find the position of all intersections of the line spanned by start and end
     pub fn intersects(&self, start: Vector2, end: Vector2) -> Vec<Vector2> {
-        let mut out = Vec::new();
+        (0..self.num_sides())
+            .filter_map(|i| self.side_intersect_time(i, start, end))
+            .map(|t| start + t * (end - start))
+            .collect()
+    }
 
-        // Vertical line
-        if start.x == end.x {
-            // For each side...
-            for i in 0..self.num_sides() {
-                let p1 = self.get(i);
-                let p2 = self.get((i + 1) % self.num_sides());
-
-                // Lines aren't both vertical and line is within x range of the side i
-                if p1.x != p2.x && p1.x.min(p2.x) <= start.x && p1.x.max(p2.x) >= start.x {
-                    // Find the y that the lines intersect at
-                    let m = (p2.y - p1.y) / (p2.x - p1.x);
-                    let b = p1.y - m * p1.x;
-                    let intersect_y = m * start.x + b;
-
-                    // If it is in the y range of the line, push to output
-                    if start.y.min(end.y) <= intersect_y && start.y.max(end.y) >= intersect_y {
-                        out.push(Vector2 { x: start.x, y: intersect_y });
-                    }
-                }
-            }
+    // Find how far along the line spanned by start and end it crosses side i, if it does
+    fn side_intersect_time(&self, i: usize, start: Vector2, end: Vector2) -> Option<Scalar> {
+        let p1 = self.get(i);
+        let p2 = self.get((i + 1) % self.num_sides());
+        let side = p2 - p1;
+        let direction = end - start;
+
+        // Ensure side and line are not parallel
+        let denominator = direction.cross(side);
+        if denominator == 0.0 {
+            return None;
+        }
+
+        // Find how far along the line and the side the intersection is. Working with
+        // these fractions, rather than the slopes of the lines, gives the same answer
+        // whichever way round the line is and whatever angle the side is at
+        let offset = p1 - start;
+        let t = offset.cross(side) / denominator;
+        let u = offset.cross(direction) / denominator;
+
+        // Make sure the intersection is on both the side and the line
+        if t >= 0.0 && t <= 1.0 && u >= 0.0 && u <= 1.0 {
+            Some(t)
         } else {
-            // Find equation of input line
-            let m1 = (end.y - start.y) / (end.x - start.x);
-            let b1 = start.y - m1 * start.x;
-
-            // For each side...
-            for i in 0..self.num_sides() {
-                let p1 = self.get(i);
-                let p2 = self.get((i + 1) % self.num_sides());
-
-                // Side is vertical
-                if p1.x == p2.x {
-                    // Ensure that side is in x range of input line
-                    if start.x.min(end.x) <= p1.x && start.x.max(end.x) >= p1.x {
-                        // Find the y that the lines intersect at
-                        let intersect_y = m1 * p1.x + b1;
-
-                        // If intersect is in the y range of the side, push to output
-                        if p1.y.min(p2.y) <= intersect_y && p1.y.max(p2.y) >= intersect_y {
-                            out.push(Vector2 { x: p1.x, y: intersect_y });
-                        }
-                    }
-                } else {
-                    // Find equation of the side
-                    let m2 = (p2.y - p1.y) / (p2.x - p1.x);
-                    let b2 = p1.y - m2 * p1.x;
-
-                    // Ensure side and line are not parallel
-                    if m1 != m2 {
-                        // Find x and y intersection
-                        let intersect_x = (b2 - b1) / (m1 - m2);
-                        let intersect_y = m1 * intersect_x + b1;
-
-                        // Make sure the intersection is on both the side and the line
-                        if intersect_x >= start.x.min(end.x) && intersect_x <= start.x.max(end.x) &&
-                            intersect_y >= start.y.min(end.y) && intersect_y <= start.y.max(end.y) &&
-                            p1.x.min(p2.x) <= intersect_x && p1.x.max(p2.x) >= intersect_x &&
-                            p1.y.min(p2.y) <= intersect_y && p1.y.max(p2.y) >= intersect_y {
-
-                            out.push(Vector2 { x: intersect_x, y: intersect_y });
-                        }
-                    }
-                }
-            }
+            None
+        }
+    }
+
+    // Find the corners of the smallest axis-aligned box containing the polygon
+    pub fn bounding_box(&self) -> (Vector2, Vector2) {
+        let mut min = Vector2 { x: INFINITY, y: INFINITY };
+        let mut max = Vector2 { x: -INFINITY, y: -INFINITY };
+
+        for v in &self.0 {
+            min.x = min.x.min(v.x);
+            min.y = min.y.min(v.y);
+            max.x = max.x.max(v.x);
+            max.y = max.y.max(v.y);
         }
 
-        out
+        (min, max)
     }
 
     pub fn contains_point(&self, pos: Vector2) -> bool {
-        self.num_intersects(pos, Vector2 { x: pos.x, y: MAX }) % 2 == 1
+        // Count the sides crossed by a ray going up from the point to past the polygon
+        let (_, max) = self.bounding_box();
+        let ray_end = Vector2 { x: pos.x, y: max.y.max(pos.y) + 1.0 };
+        self.num_intersects(pos, ray_end) % 2 == 1
     }
 }
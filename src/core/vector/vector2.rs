@@ -2,8 +2,9 @@ use crate::core::scalar::Scalar;
 use crate::core::vector::Vector;
 
 use std::ops::*;
+use serde::{Serialize, Deserialize};
 
-#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct Vector2 {
     pub x: Scalar,
     pub y: Scalar,
@@ -23,6 +24,10 @@ impl Vector2 {
     pub fn right(&self) -> Vector2 {
         vector2(self.y, -self.x)
     }
+    // The z component of the 3D cross product, positive if rhs is anticlockwise from self
+    pub fn cross(&self, rhs: Vector2) -> Scalar {
+        self.x * rhs.y - self.y * rhs.x
+    }
     pub fn as_f32_array(&self) -> [f32; 2] {
         [self.x as f32, self.y as f32]
     }
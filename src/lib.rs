@@ -0,0 +1,19 @@
+#[macro_use]
+extern crate enum_map;
+#[macro_use]
+extern crate glium;
+extern crate glium_sdl2;
+extern crate image;
+extern crate num;
+extern crate music;
+extern crate rand;
+extern crate rand_xorshift;
+extern crate sdl2;
+
+use crate::core::vector:: *;
+
+pub mod constants;
+pub mod core;
+pub mod presentation;
+pub mod simulation;
+pub mod scenes;
@@ -1,13 +1,7 @@
-#[macro_use]
-extern crate enum_map;
-#[macro_use]
+extern crate contagion;
 extern crate glium;
 extern crate glium_sdl2;
-extern crate image;
-extern crate num;
 extern crate music;
-extern crate rand;
-extern crate rand_xorshift;
 extern crate sdl2;
 
 use std::time::Instant;
@@ -16,19 +10,13 @@ use glium::draw_parameters::Blend;
 use glium_sdl2::SDL2Facade;
 use sdl2::{EventPump, Sdl};
 
-use crate::core::scalar:: *;
-use crate::core::vector:: *;
-use crate::presentation::audio::sound_effects:: *;
-use crate::scenes::main_menu;
-use crate::scenes::scene::{Scene, UpdateResult};
-use crate::simulation::update::Sound;
-use crate::presentation::graphics::font::{Font, FontPkg};
-
-pub mod constants;
-pub mod core;
-pub mod presentation;
-pub mod simulation;
-pub mod scenes;
+use contagion::core::scalar:: *;
+use contagion::presentation;
+use contagion::presentation::audio::sound_effects:: *;
+use contagion::scenes::main_menu;
+use contagion::scenes::scene::{Scene, UpdateResult};
+use contagion::simulation::update::Sound;
+use contagion::presentation::graphics::font::{Font, FontPkg};
 
 fn init() -> Result<((Sdl, SDL2Facade, EventPump),
                      presentation::display::Textures,
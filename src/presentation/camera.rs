@@ -2,8 +2,9 @@ use crate::core::scalar::*;
 use crate::core::vector::*;
 use crate::core::matrix::*;
 use glium_sdl2::SDL2Facade;
+use serde::{Serialize, Deserialize};
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Camera {
     position: Vector2,
     velocity: Vector2,
@@ -78,6 +79,12 @@ impl Camera {
         })
     }
 
+    // Jump straight to looking at the point in the world
+    pub fn center_on(&mut self, point: Vector2) {
+        self.position = Vector2 { x: point.x * self.zoom.x, y: point.y * self.zoom.y };
+        self.velocity = Vector2::zero();
+    }
+
     pub fn camera_pan(&mut self, mouse_pos_x: i32, mouse_pos_y: i32) {
         let initial_mouse_pos = &mut Vector2 {x: mouse_pos_x as f64, y: mouse_pos_y as f64};
         self.initial_mouse_pos = *initial_mouse_pos;
@@ -4,6 +4,14 @@ use crate::core::matrix::*;
 use crate::core::geo::polygon::*;
 use crate::simulation::state::*;
 use crate::simulation::control::*;
+use crate::simulation::outcome::detected_infected;
+use crate::simulation::barricade::*;
+use crate::presentation::fog_of_war::*;
+use crate::simulation::decals::*;
+use crate::presentation::graphics::decals::Decals;
+use crate::simulation::reinforcements::static_map_bounds;
+use crate::simulation::statistics::{Score, civilians_saved};
+use crate::scenes::high_scores::HighScore;
 
 use glium::Surface;
 use glium::texture::texture2d::Texture2d;
@@ -13,17 +21,30 @@ use crate::presentation::ui::gui::GuiType;
 use crate::presentation::ui::glium_text;
 use crate::presentation::ui::glium_text::FontTexture;
 use crate::presentation::ui::gui::Component;
+use crate::presentation::ui::minimap::{world_to_minimap, minimap_contains};
 use crate::presentation::graphics::font::{FontPkg};
+use crate::presentation::graphics::animation::*;
 
 // Enum ordered by draw order
 #[derive(Copy, Clone, Debug, Enum, PartialEq)]
 pub enum SpriteType {
+    BarricadeFrame,
+    Barricade,
+    BarricadeDamaged,
     SelectionHighlight,
     Dead,
     BulletCasing,
-    Civilian,
-    Zombie,
-    Cop,
+    // Body parts, with the hands of each kind of entity under its torso
+    CivilianHandLeft,
+    CivilianHandRight,
+    CivilianTorso,
+    ZombieClawLeft,
+    ZombieClawRight,
+    ZombieTorso,
+    CopLeftHand,
+    CopGunHand,
+    CopBatonHand,
+    CopTorso,
     BulletInAir,
     Menu,
     MenuWindow,
@@ -47,18 +68,38 @@ pub fn load_textures(window: &glium_sdl2::SDL2Facade) -> Textures {
 
     Textures {
         sprite_textures: enum_map! {
+            SpriteType::BarricadeFrame
+                => load_texture(window, "assets/images/other/barricade_frame.png"),
+            SpriteType::Barricade
+                => load_texture(window, "assets/images/other/barricade.png"),
+            SpriteType::BarricadeDamaged
+                => load_texture(window, "assets/images/other/barricade_damaged.png"),
             SpriteType::SelectionHighlight
                 => load_texture(window, "assets/images/other/selection_highlight.png"),
             SpriteType::Dead
                 => load_texture(window, "assets/images/old/dead_zombie.png"),
             SpriteType::BulletCasing
                 => load_texture(window, "assets/images/other/bullet_casing_straight.png"),
-            SpriteType::Civilian
-                => load_texture(window, "assets/images/old/citizen.png"),
-            SpriteType::Zombie
-                => load_texture(window, "assets/images/old/zombie.png"),
-            SpriteType::Cop
-                => load_texture(window, "assets/images/old/police.png"),
+            SpriteType::CivilianHandLeft
+                => load_texture(window, "assets/images/civilian/civilian_hand_left.png"),
+            SpriteType::CivilianHandRight
+                => load_texture(window, "assets/images/civilian/civilian_hand_right.png"),
+            SpriteType::CivilianTorso
+                => load_texture(window, "assets/images/civilian/civilian_torso.png"),
+            SpriteType::ZombieClawLeft
+                => load_texture(window, "assets/images/zombie/zombie_claw_left.png"),
+            SpriteType::ZombieClawRight
+                => load_texture(window, "assets/images/zombie/zombie_claw_right.png"),
+            SpriteType::ZombieTorso
+                => load_texture(window, "assets/images/zombie/zombie_torso.png"),
+            SpriteType::CopLeftHand
+                => load_texture(window, "assets/images/cop/cop_left_hand.png"),
+            SpriteType::CopGunHand
+                => load_texture(window, "assets/images/cop/cop_holding_gun.png"),
+            SpriteType::CopBatonHand
+                => load_texture(window, "assets/images/cop/cop_baton_hand.png"),
+            SpriteType::CopTorso
+                => load_texture(window, "assets/images/cop/cop_torso.png"),
             SpriteType::BulletInAir
                 => load_texture(window, "assets/images/other/flying_bullet_long.png"),
             SpriteType::Menu
@@ -141,6 +182,15 @@ struct Sprite {
     radius: Scalar,
 }
 
+// Position of an entity between the previous tick and the current one
+fn interpolate_position(previous_positions: &[Vector2], state: &State, index: usize, interpolation: Scalar) -> Vector2 {
+    let current = state.entities[index].position;
+    match previous_positions.get(index) {
+        Some(previous) => *previous + interpolation * (current - *previous),
+        None => current
+    }
+}
+
 fn push_sprite_vertices(buffer: &mut Vec<Vertex>, sprite: &Sprite) {
 
     let position = sprite.position;
@@ -184,6 +234,23 @@ fn push_sprite_vertices(buffer: &mut Vec<Vertex>, sprite: &Sprite) {
     buffer.push(vertex2);
 }
 
+// Stretch the texture along the barricade from one end to the other
+fn push_barricade_vertices(buffer: &mut Vec<Vertex>, barricade: &Barricade) {
+    let across = 0.5 * BARRICADE_THICKNESS * (barricade.end - barricade.start).normalize().right();
+
+    let top_left  = barricade.start + across;
+    let top_right = barricade.end + across;
+    let bot_left  = barricade.start - across;
+    let bot_right = barricade.end - across;
+
+    buffer.push(Vertex { position: top_left.as_f32_array(), tex_coords: [0.0, 1.0] });
+    buffer.push(Vertex { position: top_right.as_f32_array(), tex_coords: [1.0, 1.0] });
+    buffer.push(Vertex { position: bot_left.as_f32_array(), tex_coords: [0.0, 0.0] });
+    buffer.push(Vertex { position: top_right.as_f32_array(), tex_coords: [1.0, 1.0] });
+    buffer.push(Vertex { position: bot_right.as_f32_array(), tex_coords: [1.0, 0.0] });
+    buffer.push(Vertex { position: bot_left.as_f32_array(), tex_coords: [0.0, 0.0] });
+}
+
 // TODO: fix
 fn push_gui_vertices(buffer: &mut Vec<ColorVertex>, ui: &Gui) {
     let top_left  =  ui.top_left;
@@ -228,39 +295,117 @@ fn push_gui_vertices(buffer: &mut Vec<ColorVertex>, ui: &Gui) {
     buffer.push(vertex2);
 }
 
-// TODO: Assumes building is a rectangle and not an arbitrary polygon, consider generalizing
+// Where a cop carrying out the order is headed
+fn order_destination(state: &State, order: &CopState) -> Option<Vector2> {
+    match order {
+        CopState::Moving { waypoint, .. } => Some(*waypoint),
+        CopState::AttackingZombie { target_index, .. } => Some(state.entities[*target_index].position),
+        CopState::Garrisoning { building_index, .. } => Some(state.shelters[*building_index].door),
+        CopState::Patrolling { waypoints, next, .. } => Some(waypoints[*next]),
+        CopState::Guarding { anchor, .. } => Some(*anchor),
+        _ => None
+    }
+}
+
 fn push_building_vertices(buffer: &mut Vec<ColorVertex>, building: &Polygon, color: [f32; 4]) {
-    let top_left = building.get(0);
-    let top_right = building.get(1);
-    let bot_right = building.get(2);
-    let bot_left = building.get(3);
+    // Stretch the texture over the building's bounding box
+    let (min, max) = building.bounding_box();
+    let size = max - min;
+
+    for triangle in building.triangulate() {
+        for corner in &triangle {
+            buffer.push(ColorVertex {
+                position: corner.as_f32_array(),
+                tex_coords: [((corner.x - min.x) / size.x) as f32, ((corner.y - min.y) / size.y) as f32],
+                color
+            });
+        }
+    }
+}
 
-    let vertex0 = ColorVertex {
-        position: top_left.as_f32_array(),
-        tex_coords: [0.0, 1.0],
-        color
-    };
-    let vertex1 = ColorVertex {
-        position: top_right.as_f32_array(),
-        tex_coords: [1.0, 1.0],
-        color
-    };
-    let vertex2 = ColorVertex {
-        position: bot_left.as_f32_array(),
-        tex_coords: [0.0, 0.0],
-        color
-    };
-    let vertex3 = ColorVertex {
-        position: bot_right.as_f32_array(),
-        tex_coords: [1.0, 0.0],
-        color
-    };
-    buffer.push(vertex0);
-    buffer.push(vertex1);
-    buffer.push(vertex2);
-    buffer.push(vertex1);
-    buffer.push(vertex3);
-    buffer.push(vertex2);
+fn push_rectangle_vertices(buffer: &mut Vec<ColorVertex>, min: Vector2, max: Vector2, color: [f32; 4]) {
+    let rectangle = Polygon(vec![min, vector2(max.x, min.y), max, vector2(min.x, max.y)]);
+    push_building_vertices(buffer, &rectangle, color);
+}
+
+// Draw the buildings, everyone the player can see and the outline of the viewport onto
+// the minimap
+fn push_minimap_vertices(
+    buffer: &mut Vec<ColorVertex>,
+    minimap: &Gui,
+    state: &State,
+    fog_of_war: Option<&FogOfWar>,
+    viewport: (Vector2, Vector2),
+    aspect_ratio: Scalar) {
+
+    let bounds = static_map_bounds(state);
+    let to_minimap = |point: Vector2| world_to_minimap(minimap, bounds, aspect_ratio, point);
+
+    push_rectangle_vertices(buffer, minimap.bot_left, minimap.top_right, [0.0, 0.0, 0.0, 0.6]);
+
+    for building in &state.buildings {
+        let outline = Polygon(building.0.iter().map(|corner| to_minimap(*corner)).collect());
+        push_building_vertices(buffer, &outline, [0.4, 0.4, 0.4, 1.0]);
+    }
+
+    // Square on the screen, however wide the window
+    let half_size = vector2(0.005, 0.005 * aspect_ratio);
+    for entity in &state.entities {
+        if entity.sheltering_in.is_some() {
+            continue;
+        }
+        let color = match entity.behaviour {
+            Behaviour::Cop { .. } => [0.2, 0.4, 1.0, 1.0],
+            Behaviour::Human { .. } => [0.2, 0.8, 0.2, 1.0],
+            Behaviour::Zombie { .. } if fog_of_war.map_or(true, |fog_of_war| fog_of_war.is_visible(entity.position)) =>
+                [0.9, 0.1, 0.1, 1.0],
+            _ => continue,
+        };
+        // Those who have wandered off the edge of the map aren't shown
+        let dot = to_minimap(entity.position);
+        if !minimap_contains(minimap, dot) {
+            continue;
+        }
+        push_rectangle_vertices(buffer, dot - half_size, dot + half_size, color);
+    }
+
+    // Outline the viewport, cut off at the edges of the minimap
+    let clamp = |point: Vector2| vector2(
+        point.x.max(minimap.bot_left.x).min(minimap.top_right.x),
+        point.y.max(minimap.bot_left.y).min(minimap.top_right.y));
+    let min = clamp(to_minimap(viewport.0));
+    let max = clamp(to_minimap(viewport.1));
+    let thickness = 0.004;
+    let color = [1.0, 1.0, 1.0, 0.8];
+    push_rectangle_vertices(buffer, min, vector2(max.x, min.y + thickness), color);
+    push_rectangle_vertices(buffer, vector2(min.x, max.y - thickness), max, color);
+    push_rectangle_vertices(buffer, min, vector2(min.x + thickness, max.y), color);
+    push_rectangle_vertices(buffer, vector2(max.x - thickness, min.y), max, color);
+}
+
+// Draw each mark on the ground as an oval stretched along its angle, fading the older
+// marks that are soon to be replaced
+fn push_decal_vertices(buffer: &mut Vec<ColorVertex>, decals: &Decals) {
+    const SIDES: usize = 8;
+    let count = decals.decals.len();
+
+    for (age_rank, decal) in decals.iter().enumerate() {
+        let (stretch, color) = match decal.kind {
+            DecalKind::BloodSplat => (1.5, [0.45, 0.0, 0.0, 0.8]),
+            DecalKind::DragTrail => (3.0, [0.35, 0.0, 0.0, 0.6]),
+            DecalKind::Scorch => (1.0, [0.05, 0.05, 0.05, 0.7]),
+        };
+        let freshness = 0.5 + 0.5 * (age_rank + 1) as f32 / count as f32;
+        let color = [color[0], color[1], color[2], color[3] * freshness];
+
+        let along = Vector2::from_angle(decal.angle);
+        let across = along.right();
+        let oval = Polygon((0..SIDES).map(|i| {
+            let angle = 2.0 * std::f64::consts::PI * i as Scalar / SIDES as Scalar;
+            decal.position + decal.radius * (stretch * angle.cos() * along + angle.sin() * across)
+        }).collect());
+        push_building_vertices(buffer, &oval, color);
+    }
 }
 
 fn push_path_vertices(buffer: &mut Vec<ColorVertex>, point1: Vector2, point2: Vector2, color: [f32; 4]) {
@@ -403,23 +548,41 @@ pub fn display(
     programs: &Programs,
     textures: &Textures,
     params: &glium::DrawParameters,
-    state: &State, camera_frame: Mat4,
+    state: &State,
+    previous_positions: &[Vector2],
+    interpolation: Scalar,
+    camera_frame: Mat4,
     ui: &mut Component,
     fonts: &FontPkg,
-    control: &Control
+    control: &Control,
+    fog_of_war: Option<&FogOfWar>,
+    animations: &Animations,
+    decals: &Decals
 ) {
     let font = fonts.get("Consola").unwrap();
 
     frame.clear_color(0.2, 0.2, 0.2, 1.0);
 
+    // The part of the map in view, from the bottom left corner of the screen to the top right
+    let inverse_camera_frame = camera_frame.inverse_matrix4();
+    let viewport = (inverse_camera_frame.multiply_vec2(vector2(-1.0, -1.0)),
+                    inverse_camera_frame.multiply_vec2(vector2(1.0, 1.0)));
+
     let camera_frame = camera_frame.as_f32_array();
 
+    let (width, height) = frame.get_dimensions();
+    let aspect_ratio = width as Scalar / height as Scalar;
+
     draw_background(frame, window, textures, programs, camera_frame, params);
 
     let mut vertex_buffers = enum_map!{_ => vec!()};
     let mut vertex_buffers_gui = enum_map!{_ => vec!()};
+    let mut vertex_buffers_ground = vec!();
+    let mut vertex_buffers_decal = vec!();
     let mut vertex_buffers_building = vec!();
     let mut vertex_buffers_path = vec!();
+    let mut vertex_buffers_fog = vec!();
+    let mut vertex_buffers_minimap = vec!();
     let mut text_buffers = vec!();
 
     let mut cop_count = 0;
@@ -445,20 +608,54 @@ pub fn display(
         push_sprite_vertices(&mut vertex_buffers[sprite_type], &sprite);
     }
 
-    // Compute the vertices in world coordinates of all entities
-    for entity in &state.entities {
-        let sprite_type = match entity.behaviour {
-            Behaviour::Cop{..} => {cop_count+=1; SpriteType::Cop},
-            Behaviour::Dead => {_dead_count+=1; SpriteType::Dead},
-            Behaviour::Human => {human_count+=1; SpriteType::Civilian},
-            Behaviour::Zombie{..} => {zombie_count+=1; SpriteType::Zombie},
+    // Compute the vertices of barricades, showing how far along or how battered they are
+    for barricade in &state.barricades {
+        let sprite_type = if !barricade.is_built() {
+            SpriteType::BarricadeFrame
+        } else if barricade.is_destroyed() {
+            continue;
+        } else if barricade.health < 0.5 * BARRICADE_MAX_HEALTH {
+            SpriteType::BarricadeDamaged
+        } else {
+            SpriteType::Barricade
         };
-        let sprite = Sprite {
-            position: entity.position,
-            facing: entity.get_facing_normal(),
-            radius: 0.5,
+        push_barricade_vertices(&mut vertex_buffers[sprite_type], barricade);
+    }
+
+    // Compute the vertices in world coordinates of all entities
+    for i in 0..state.entities.len() {
+        let entity = &state.entities[i];
+        match entity.behaviour {
+            Behaviour::Cop{..} => cop_count+=1,
+            Behaviour::Dead => _dead_count+=1,
+            Behaviour::Human { .. } => human_count+=1,
+            Behaviour::Zombie{..} => zombie_count+=1,
         };
-        push_sprite_vertices(&mut vertex_buffers[sprite_type], &sprite);
+        // Those hiding in buildings are off the map
+        if entity.sheltering_in.is_some() {
+            continue;
+        }
+        let mut position = interpolate_position(previous_positions, state, i, interpolation);
+        // Zombies out of sight are shown where they were last seen, if they have been
+        if let (Behaviour::Zombie { .. }, Some(fog_of_war)) = (&entity.behaviour, fog_of_war) {
+            if !fog_of_war.is_visible(entity.position) {
+                match fog_of_war.last_seen(i) {
+                    Some(last_seen) => position = last_seen,
+                    None => continue,
+                }
+            }
+        }
+        // Put the entity together from its body parts, posed for what it is doing
+        let facing = entity.get_facing_normal();
+        let right = facing.right();
+        for part in body_parts(entity, animations.walk_phase(i)) {
+            let sprite = Sprite {
+                position: position + part.offset.x * right + part.offset.y * facing,
+                facing: facing.rotate_by(part.angle),
+                radius: part.radius,
+            };
+            push_sprite_vertices(&mut vertex_buffers[part.sprite_type], &sprite);
+        }
     }
 
     // Compute vertices for selection highlights
@@ -471,7 +668,7 @@ pub fn display(
             };
             let entity = &state.entities[*i];
             let sprite = Sprite {
-                position: entity.position,
+                position: interpolate_position(previous_positions, state, *i, interpolation),
                 facing: entity.get_facing_normal(),
                 radius: 0.5,
             };
@@ -539,15 +736,57 @@ pub fn display(
                     push_gui_vertices(&mut vertex_buffers_gui[SpriteType::MenuWindow], _window_gui);
                 }
             },
-            _ => (),
+            GuiType::Button {..} => {
+                if ui.active_window == ActiveWindow::Game {
+                    text_buffers.push(Box::new(component.clone()));
+                    _menu_buttons.push(component.get_dimension());
+                    push_gui_vertices(&mut vertex_buffers_gui[SpriteType::Button], component);
+                }
+            },
+            GuiType::Minimap => {
+                if ui.active_window == ActiveWindow::Game {
+                    push_minimap_vertices(&mut vertex_buffers_minimap, component, state, fog_of_war, viewport, aspect_ratio);
+                }
+            },
         };
     }
 
     //  push_gui_vertices(&mut vertex_buffers_gui[SpriteType::SelectionHighlight], component);
 
-    // Compute vertices for buildings
-    for building in &state.buildings {
-        let color = [0.1, 0.1, 0.1, 1.0];
+    // Compute vertices for roads and plazas
+    for road in &state.roads {
+        let color = [0.25, 0.25, 0.25, 0.5];
+        push_building_vertices(&mut vertex_buffers_ground, road, color);
+    }
+    for plaza in &state.plazas {
+        let color = [0.6, 0.55, 0.45, 0.3];
+        push_building_vertices(&mut vertex_buffers_ground, plaza, color);
+    }
+    for checkpoint in &state.checkpoints {
+        let color = [0.2, 0.3, 0.7, 0.3];
+        push_building_vertices(&mut vertex_buffers_ground, checkpoint, color);
+    }
+
+    // Compute vertices for blood and scorch marks
+    push_decal_vertices(&mut vertex_buffers_decal, decals);
+
+    // Compute vertices for doors, which turn red once breached
+    for shelter in &state.shelters {
+        let half_size = vector2(ENTITY_RADIUS, ENTITY_RADIUS);
+        let door = Polygon(vec![
+            shelter.door - half_size,
+            shelter.door + vector2(half_size.x, -half_size.y),
+            shelter.door + half_size,
+            shelter.door + vector2(-half_size.x, half_size.y),
+        ]);
+        let color = if shelter.is_breached() { [0.7, 0.1, 0.1, 0.8] } else { [0.45, 0.3, 0.15, 0.8] };
+        push_building_vertices(&mut vertex_buffers_ground, &door, color);
+    }
+
+    // Compute vertices for buildings, lighter if someone is hiding inside
+    for (i, building) in state.buildings.iter().enumerate() {
+        let occupied = state.shelters.get(i).map_or(false, |shelter| !shelter.occupants.is_empty());
+        let color = if occupied { [0.2, 0.2, 0.3, 1.0] } else { [0.1, 0.1, 0.1, 1.0] };
         push_building_vertices(&mut vertex_buffers_building, building, color);
     }
 
@@ -558,6 +797,10 @@ pub fn display(
                 let path = match state_stack.last() {
                     Some(CopState::Moving { path, .. }) => path,
                     Some(CopState::AttackingZombie { path, .. }) => path,
+                    Some(CopState::Garrisoning { path, .. }) => path,
+                    Some(CopState::Building { path, .. }) => path,
+                    Some(CopState::Patrolling { path, .. }) => path,
+                    Some(CopState::Guarding { path, .. }) => path,
                     _ => &None
                 };
                 match path {
@@ -575,6 +818,90 @@ pub fn display(
         };
     }
 
+    // Compute vertices for the chain of orders queued up after each cop's current one
+    for entity in &state.entities {
+        if let Behaviour::Cop { state_stack, queued_orders, .. } = &entity.behaviour {
+            let mut previous = state_stack.first()
+                .and_then(|order| order_destination(state, order))
+                .unwrap_or(entity.position);
+            let color = [0.0, 0.0, 0.0, 0.5];
+            for order in queued_orders {
+                if let Some(destination) = order_destination(state, order) {
+                    push_path_vertices(&mut vertex_buffers_path, previous, destination, color);
+                    previous = destination;
+                }
+            }
+        }
+    }
+
+    // Compute vertices for the loop each patrolling cop walks
+    for entity in &state.entities {
+        if let Behaviour::Cop { state_stack, .. } = &entity.behaviour {
+            if let Some(CopState::Patrolling { waypoints, .. }) = state_stack.first() {
+                let color = [0.0, 0.0, 0.4, 0.5];
+                for i in 0..waypoints.len() {
+                    push_path_vertices(&mut vertex_buffers_path, waypoints[i], waypoints[(i + 1) % waypoints.len()], color);
+                }
+            }
+        }
+    }
+
+    // Compute vertices for the fog over everything out of sight. Buildings are left
+    // clear, since the layout of the city is no secret
+    if let Some(fog_of_war) = fog_of_war {
+        let half_size = 0.5 * vector2(FOG_CELL_SIZE, FOG_CELL_SIZE);
+        let color = [0.0, 0.0, 0.0, 0.5];
+        for cell in 0..fog_of_war.visible.len() {
+            if fog_of_war.visible[cell] || fog_of_war.blocked[cell] {
+                continue;
+            }
+            let center = fog_of_war.cell_center(cell);
+            let square = Polygon(vec![
+                center - half_size,
+                center + vector2(half_size.x, -half_size.y),
+                center + half_size,
+                center + vector2(-half_size.x, half_size.y),
+            ]);
+            push_building_vertices(&mut vertex_buffers_fog, &square, color);
+        }
+    }
+
+    // Compute vertices for the patrol still being marked out
+    if control.placing_patrol {
+        let color = [0.0, 0.0, 0.4, 1.0];
+        for i in 1..control.patrol_waypoints.len() {
+            push_path_vertices(&mut vertex_buffers_path, control.patrol_waypoints[i - 1], control.patrol_waypoints[i], color);
+        }
+    }
+
+
+    // Render roads and plazas
+    {
+        let uniforms = uniform! {
+            matrix: camera_frame
+        };
+        draw_color_sprites(
+            frame,
+            window,
+            &vertex_buffers_ground,
+            &programs.shape_program,
+            params,
+            &uniforms);
+    }
+
+    // Render blood and scorch marks, over the roads but under everyone standing on them
+    {
+        let uniforms = uniform! {
+            matrix: camera_frame
+        };
+        draw_color_sprites(
+            frame,
+            window,
+            &vertex_buffers_decal,
+            &programs.shape_program,
+            params,
+            &uniforms);
+    }
 
     // Render paths
     {
@@ -607,7 +934,7 @@ pub fn display(
 
     // Render shadows
     use crate::presentation::display::SpriteType::*;
-    for sprite_type in &[Cop, Civilian, Dead, Zombie] {
+    for sprite_type in &[CopTorso, CivilianTorso, Dead, ZombieTorso] {
 
         let uniforms = uniform! {
             matrix: camera_frame,
@@ -639,6 +966,20 @@ pub fn display(
             &uniforms);
     }
 
+    // Render fog
+    {
+        let uniforms = uniform! {
+            matrix: camera_frame
+        };
+        draw_color_sprites(
+            frame,
+            window,
+            &vertex_buffers_fog,
+            &programs.shape_program,
+            params,
+            &uniforms);
+    }
+
     // Render GUI
     let mat_gui = [
         [1.0, 0.0, 0.0, 0.0],
@@ -647,19 +988,7 @@ pub fn display(
         [0.0, 0.0, 0.0, 1.0f32],
     ];
     for (_gui_type, vertex_buffer) in &vertex_buffers_gui {
-        if _gui_type == SpriteType::Cop {
-            let uniforms = uniform! {
-                    matrix: mat_gui,
-                    tex: &textures.sprite_textures[SpriteType::Cop],
-                };
-            draw_color_sprites(
-                frame,
-                window,
-                &vertex_buffer,
-                &programs.sprite_program,
-                params,
-                &uniforms);
-        } else if _gui_type == SpriteType::SelectionHighlight {
+        if _gui_type == SpriteType::SelectionHighlight {
             let uniforms = uniform! {
                     matrix: mat_gui,
                 };
@@ -795,6 +1124,28 @@ pub fn display(
 
     }
 
+    // Render minimap
+    {
+        let uniforms = uniform! {
+            matrix: mat_gui,
+        };
+        draw_color_sprites(
+            frame,
+            window,
+            &vertex_buffers_minimap,
+            &programs.gui_program,
+            params,
+            &uniforms);
+    }
+
+    // Show the infected count only when the player has a way of telling who is infected
+    if let Some(infected_count) = detected_infected(state) {
+        draw_infected_num(window, infected_count, frame, &font.lowres());
+    }
+
+    draw_reinforcements_timer(window, state, control, frame, &font.lowres());
+    draw_formation(window, control, frame, &font.lowres());
+
     // Render Menu Text
     let mat = Mat4::init_id_matrix();
     for i in 0..text_buffers.len() {
@@ -851,6 +1202,24 @@ fn draw_cop_num(window: &glium_sdl2::SDL2Facade, cop_num: i32, frame: &mut glium
 }
 
 
+// Draw a line of status text along the bottom of the screen
+pub fn draw_status_text(frame: &mut glium::Frame, window: &glium_sdl2::SDL2Facade, fonts: &FontPkg, status: &str) {
+    let font = fonts.get("Consola").unwrap();
+    let system = glium_text::TextSystem::new(window);
+    let text = glium_text::TextDisplay::new(&system, font.lowres(), status);
+    let color = [1.0, 1.0, 1.0, 1.0f32];
+    let font_scale_down = 40.0;
+    let (w, h) = frame.get_dimensions();
+    let matrix = [
+        [1.0/font_scale_down, 0.0, 0.0, 0.0],
+        [0.0, 1.0 * (w as f32) / (h as f32) / font_scale_down,0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [-0.3, -0.95, 0.0, 1.0f32],
+    ];
+
+    glium_text::draw(&text, &system, frame, matrix, color);
+}
+
 // Draw the remaining number of zombies in the world (number)
 fn draw_remaining_zombie_num(window: &glium_sdl2::SDL2Facade, zombie_num: i32, frame: &mut glium::Frame, font: &FontTexture){
     let system = glium_text::TextSystem::new(window);
@@ -935,6 +1304,76 @@ fn draw_remaining_cop_num(window: &glium_sdl2::SDL2Facade, cop_num: i32, frame:
 
 }
 
+fn draw_infected_num(window: &glium_sdl2::SDL2Facade, infected_num: usize, frame: &mut glium::Frame, font: &FontTexture){
+    let system = glium_text::TextSystem::new(window);
+    let infected_num_display = format!("Infected: {}", infected_num);
+    let text = glium_text::TextDisplay::new(&system, font, &infected_num_display[..]);
+    let color = [0.6, 0.0, 0.0, 1.0f32];
+    let font_scale_down = 40.0;
+    let (w, h) = frame.get_dimensions();
+
+    let matrix = [
+        [1.0/font_scale_down, 0.0, 0.0, 0.0],
+        [0.0, 1.0 * (w as f32) / (h as f32) / font_scale_down,0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.5755, 0.75, 0.0, 1.0f32],
+    ];
+
+    glium_text::draw(&text, &system, frame, matrix, color);
+
+}
+
+// Draw which formation the cops will take up, above the reinforcements timer
+fn draw_formation(window: &glium_sdl2::SDL2Facade, control: &Control, frame: &mut glium::Frame, font: &FontTexture){
+    let system = glium_text::TextSystem::new(window);
+    let formation_display = format!("Formation: {} (F)", control.formation.name());
+    let text = glium_text::TextDisplay::new(&system, font, &formation_display[..]);
+    let color = [1.0, 1.0, 1.0, 1.0f32];
+    let font_scale_down = 40.0;
+    let (w, h) = frame.get_dimensions();
+
+    let matrix = [
+        [1.0/font_scale_down, 0.0, 0.0, 0.0],
+        [0.0, 1.0 * (w as f32) / (h as f32) / font_scale_down,0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.45, -0.76, 0.0, 1.0f32],
+    ];
+
+    glium_text::draw(&text, &system, frame, matrix, color);
+
+}
+
+// Draw how long until reinforcements can be called in or arrive, above the button
+fn draw_reinforcements_timer(window: &glium_sdl2::SDL2Facade, state: &State, control: &Control, frame: &mut glium::Frame, font: &FontTexture){
+    let system = glium_text::TextSystem::new(window);
+    let reinforcements = &state.reinforcements;
+    let timer_display = if let Some(squad) = reinforcements.incoming.first() {
+        format!("Squad arriving in {}s", squad.time_until_arrival.ceil())
+    } else if !reinforcements.ready() {
+        format!("Next squad in {}s", reinforcements.cooldown_remaining.ceil())
+    } else if let Some(edge) = control.reinforcement_edge {
+        format!("Entering from the {:?} edge, right click to pick a rally point", edge)
+    } else if control.calling_reinforcements {
+        "Right click near the edge to enter from".to_string()
+    } else {
+        "Reinforcements ready".to_string()
+    };
+    let text = glium_text::TextDisplay::new(&system, font, &timer_display[..]);
+    let color = [1.0, 1.0, 1.0, 1.0f32];
+    let font_scale_down = 40.0;
+    let (w, h) = frame.get_dimensions();
+
+    let matrix = [
+        [1.0/font_scale_down, 0.0, 0.0, 0.0],
+        [0.0, 1.0 * (w as f32) / (h as f32) / font_scale_down,0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.45, -0.83, 0.0, 1.0f32],
+    ];
+
+    glium_text::draw(&text, &system, frame, matrix, color);
+
+}
+
 pub fn display_main_menu (
     frame: &mut glium::Frame,
     window: &glium_sdl2::SDL2Facade,
@@ -1106,6 +1545,8 @@ pub fn display_loss_screen (
     params: &glium::DrawParameters,
     ui: &mut Component,
     state: &State,
+    score: &Score,
+    high_scores: &Vec<HighScore>,
     fonts: &FontPkg,
 ) {
     let font = fonts.get("Consola").unwrap();
@@ -1189,31 +1630,6 @@ pub fn display_loss_screen (
         glium_text::draw(&text, &system, frame, menu_matrix, color);
     }
 
-    // Render Entities
-    let mat  = Mat4::init_id_matrix();
-    let mut cop_count = 0;
-    let mut _dead_count = 0;
-    let mut human_count = 0;
-    let mut zombie_count = 0;
-    let mut score ;
-    for entity in &state.entities {
-        match entity.behaviour {
-            Behaviour::Cop{..} => {cop_count+=1;},
-            Behaviour::Dead => {_dead_count+=1;},
-            Behaviour::Human => {human_count+=1;},
-            Behaviour::Zombie{..} => {zombie_count+=1;},
-        };
-//        // score is total of alive humans
-//        score += cop_count + human_count;
-    }
-//    // score is then multiplied by 100 then subtract zombie and dead count
-//    score = score*100 - zombie_count;
-//    if score < 0 {score = 0};
-    // score is total of alive humans
-    score = cop_count + human_count;
-    score = (score - zombie_count) * 100;
-    if score < 0 {score = 0};
-
     let system = glium_text::TextSystem::new(window);
     let text_1_loss = "Humanity Perished...".to_string();
     let text_display = format!("{}", text_1_loss);
@@ -1226,27 +1642,10 @@ pub fn display_loss_screen (
     let _text_offset = 1.0 / text_width;
     let  scale_factor = Vector4 {x: 2.0/text_width, y: 2.0 * (w as f64) / (h as f64) / text_width , z: 1.0, w: 1.0};
     let  translation_offset = Vector4{x: -1.0, y: 0.3, z: 0.0, w: 0.0};
-    let mut matrix = mat.scale(scale_factor).translation(translation_offset);
+    let matrix = mat.scale(scale_factor).translation(translation_offset);
     glium_text::draw(&text, &system, frame, matrix.as_f32_array(), color);
 
-    // Score
-    let text_display = format!("Score: {}", score);
-    let str_slice: &str = &text_display[..];
-    let text = glium_text::TextDisplay::new(&system, font.medres(), str_slice);
-    let color = [1.0, 1.0, 0.0, 1.0f32];
-    let scale_factor = Vector4 {x: 0.5, y:0.5, z: 1.0, w: 1.0};
-    let translate_offset = Vector4{x: 0.1, y: -0.2, z: 0.0, w: 0.0};
-    matrix = matrix.scale(scale_factor).translation(translate_offset);
-    glium_text::draw(&text, &system, frame, matrix.as_f32_array(), color);
-
-    // Stats
-    let text_display = format!("Cops: {}, Civilians: {}, Zombies: {}", cop_count, human_count, zombie_count);
-    let str_slice: &str = &text_display[..];
-    let text = glium_text::TextDisplay::new(&system, font.medres(), str_slice);
-    let color = [1.0, 1.0, 0.0, 1.0f32];
-    let translate_offset = Vector4{x: 0.0, y: -0.2, z: 0.0, w: 0.0};
-    matrix = matrix.translation(translate_offset);
-    glium_text::draw(&text, &system, frame, matrix.as_f32_array(), color);
+    draw_match_summary(window, state, score, high_scores, frame, &font.lowres());
 }
 
 pub fn display_victory_screen (
@@ -1257,6 +1656,8 @@ pub fn display_victory_screen (
     params: &glium::DrawParameters,
     ui: &mut Component,
     state: &State,
+    score: &Score,
+    high_scores: &Vec<HighScore>,
     fonts: &FontPkg,
 ) {
     let font = fonts.get("Consola").unwrap();
@@ -1343,25 +1744,6 @@ pub fn display_victory_screen (
         glium_text::draw(&text, &system, frame, matrix, color);
     }
 
-    // Render Entities
-    let mut cop_count = 0;
-    let mut _dead_count = 0;
-    let mut human_count = 0;
-    let mut zombie_count = 0;
-    let mut score ;
-    for entity in &state.entities {
-        match entity.behaviour {
-            Behaviour::Cop{..} => {cop_count+=1;},
-            Behaviour::Dead => {_dead_count+=1;},
-            Behaviour::Human => {human_count+=1;},
-            Behaviour::Zombie{..} => {zombie_count+=1;},
-        };
-    }
-    // score is total of alive humans
-    score = cop_count + human_count;
-    score = (score - zombie_count) * 100;
-    if score < 0 {score = 0};
-
     let system = glium_text::TextSystem::new(window);
     let text_1_win = "Humanity Prevailed!".to_string();
     let text_display = format!("{}", text_1_win);
@@ -1374,25 +1756,71 @@ pub fn display_victory_screen (
     let _text_offset = 1.0 / text_width;
     let  scale_factor = Vector4 {x: 2.0/text_width, y: 2.0 * (w as f64) / (h as f64) / text_width , z: 1.0, w: 1.0};
     let  translation_offset = Vector4{x: -1.0, y: 0.3, z: 0.0, w: 0.0};
-    let mut matrix = mat.scale(scale_factor).translation(translation_offset);
+    let matrix = mat.scale(scale_factor).translation(translation_offset);
     glium_text::draw(&text, &system, frame, matrix.as_f32_array(), color);
 
-    // Score
-    let text_display = format!("Score: {}", score);
-    let str_slice: &str = &text_display[..];
-    let text = glium_text::TextDisplay::new(&system, font.medres(), str_slice);
-    let color = [1.0, 1.0, 0.0, 1.0f32];
-    let scale_factor = Vector4 {x: 0.5, y:0.5, z: 1.0, w: 1.0};
-    let translate_offset = Vector4{x: 0.1, y: -0.2, z: 0.0, w: 0.0};
-    matrix = matrix.scale(scale_factor).translation(translate_offset);
-    glium_text::draw(&text, &system, frame, matrix.as_f32_array(), color);
+    draw_match_summary(window, state, score, high_scores, frame, &font.lowres());
+}
 
-    // Stats
-    let text_display = format!("Cops: {}, Civilians: {}, Zombies: {}", cop_count, human_count, zombie_count);
-    let str_slice: &str = &text_display[..];
-    let text = glium_text::TextDisplay::new(&system, font.medres(), str_slice);
-    let color = [1.0, 1.0, 0.0, 1.0f32];
-    let translate_offset = Vector4{x: 0.0, y: -0.2, z: 0.0, w: 0.0};
-    matrix = matrix.translation(translate_offset);
-    glium_text::draw(&text, &system, frame, matrix.as_f32_array(), color);
+// Draw a line of text at a position on the end screens
+fn draw_summary_line(window: &glium_sdl2::SDL2Facade, line: &str, position: Vector2, color: [f32; 4], frame: &mut glium::Frame, font: &FontTexture){
+    let system = glium_text::TextSystem::new(window);
+    let text = glium_text::TextDisplay::new(&system, font, line);
+    let font_scale_down = 40.0;
+    let (w, h) = frame.get_dimensions();
+
+    let matrix = [
+        [1.0/font_scale_down, 0.0, 0.0, 0.0],
+        [0.0, 1.0 * (w as f32) / (h as f32) / font_scale_down,0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [position.x as f32, position.y as f32, 0.0, 1.0f32],
+    ];
+
+    glium_text::draw(&text, &system, frame, matrix, color);
+}
+
+// Draw the score with its breakdown and the match statistics down the left of the
+// end screens, and the high score table down the right
+fn draw_match_summary(window: &glium_sdl2::SDL2Facade, state: &State, score: &Score, high_scores: &Vec<HighScore>, frame: &mut glium::Frame, font: &FontTexture){
+    // Small enough for everything to fit between the title and the buttons
+    const LINE_HEIGHT: f64 = 0.055;
+    const MAX_COPS_LISTED: usize = 3;
+    let statistics = &state.statistics;
+    let yellow = [1.0, 1.0, 0.0, 1.0f32];
+    let white = [1.0, 1.0, 1.0, 1.0f32];
+
+    let mut lines = vec!();
+    lines.push((format!("Score: {}", score.total()), yellow));
+    for (name, points) in score.breakdown() {
+        lines.push((format!("  {:<16}{:>+7}", name, points), white));
+    }
+    let accuracy = match statistics.accuracy() {
+        Some(accuracy) => format!("{:.0}%", 100.0 * accuracy),
+        None => "-".to_string(),
+    };
+    lines.push((format!("Shots: {}  Hits: {}  Accuracy: {}", statistics.shots_fired, statistics.hits, accuracy), yellow));
+    lines.push((format!("Civilians saved: {}  lost: {}", civilians_saved(state), statistics.civilians_lost), yellow));
+    if let Some(time) = statistics.time_to_victory {
+        lines.push((format!("Time to victory: {}:{:02}", (time / 60.0) as u32, (time % 60.0) as u32), yellow));
+    }
+
+    // The cops with the most kills
+    let mut kills: Vec<(&usize, &u32)> = statistics.zombies_killed.iter().collect();
+    kills.sort_by(|a, b| b.1.cmp(a.1));
+    for (cop_index, count) in kills.into_iter().take(MAX_COPS_LISTED) {
+        lines.push((format!("  Cop #{}: {} kills", cop_index, count), white));
+    }
+
+    for (i, (line, color)) in lines.iter().enumerate() {
+        let position = Vector2 { x: -0.9, y: 0.2 - LINE_HEIGHT * i as f64 };
+        draw_summary_line(window, line, position, *color, frame, font);
+    }
+
+    draw_summary_line(window, "High scores", Vector2 { x: 0.3, y: 0.2 }, yellow, frame, font);
+    for (i, high_score) in high_scores.iter().enumerate() {
+        let result = if high_score.won { "won" } else { "lost" };
+        let line = format!("{:>2}. {:>6}  {} ({})", i + 1, high_score.score, high_score.scenario, result);
+        let position = Vector2 { x: 0.3, y: 0.2 - LINE_HEIGHT * (i + 1) as f64 };
+        draw_summary_line(window, &line, position, white, frame, font);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,138 @@
+use crate::core::vector::*;
+use crate::core::scalar::*;
+use crate::simulation::ai::pathfinding::can_see;
+use crate::simulation::reinforcements::static_map_bounds;
+use crate::simulation::state::*;
+
+// Side length of the square cells the map is divided into to work out what can be seen
+pub const FOG_CELL_SIZE: Scalar = 4.0;
+
+// How far the cells reach past the buildings, roads and plazas
+const FOG_MARGIN: Scalar = 20.0;
+
+// What the cops can see of the map, and where each entity was last seen. It only affects
+// what the player is shown, never the simulation
+#[derive(Clone)]
+pub struct FogOfWar {
+    // Corner of the first cell
+    pub origin: Vector2,
+    pub columns: usize,
+    pub rows: usize,
+    // Whether anyone can see the centre of each cell, row by row
+    pub visible: Vec<bool>,
+    // Cells with their centre inside a building, which no one can see into
+    pub blocked: Vec<bool>,
+    // Where each entity was when last seen, at the same index as the entity
+    pub last_seen: Vec<Option<Vector2>>,
+    // Where the cops on the map are. Points beyond the cells are seen by any cop close
+    // enough, since there is nothing out there to block the view
+    pub viewers: Vec<Vector2>,
+}
+
+impl FogOfWar {
+    pub fn new(state: &State) -> FogOfWar {
+        let (min, max) = static_map_bounds(state);
+        let margin = vector2(FOG_MARGIN, FOG_MARGIN);
+        let (min, max) = (min - margin, max + margin);
+        let size = max - min;
+        let columns = ((size.x / FOG_CELL_SIZE).ceil() as usize).max(1);
+        let rows = ((size.y / FOG_CELL_SIZE).ceil() as usize).max(1);
+
+        let mut fog_of_war = FogOfWar {
+            origin: min,
+            columns,
+            rows,
+            visible: vec![false; columns * rows],
+            blocked: vec!(),
+            last_seen: vec!(),
+            viewers: vec!(),
+        };
+        fog_of_war.blocked = (0..columns * rows)
+            .map(|cell| {
+                let center = fog_of_war.cell_center(cell);
+                state.buildings.iter().any(|building| building.contains_point(center))
+            })
+            .collect();
+        fog_of_war.update(state);
+        fog_of_war
+    }
+
+    pub fn cell_center(&self, cell: usize) -> Vector2 {
+        let column = (cell % self.columns) as Scalar;
+        let row = (cell / self.columns) as Scalar;
+        self.origin + FOG_CELL_SIZE * vector2(column + 0.5, row + 0.5)
+    }
+
+    // The cell containing the point, if it is on the map
+    fn cell_at(&self, point: Vector2) -> Option<usize> {
+        let offset = (point - self.origin) / FOG_CELL_SIZE;
+        if offset.x < 0.0 || offset.y < 0.0 {
+            return None;
+        }
+
+        let (column, row) = (offset.x as usize, offset.y as usize);
+        if column >= self.columns || row >= self.rows {
+            return None;
+        }
+        Some(row * self.columns + column)
+    }
+
+    pub fn is_visible(&self, point: Vector2) -> bool {
+        match self.cell_at(point) {
+            Some(cell) => self.visible[cell],
+            None => self.viewers.iter()
+                .any(|viewer| (*viewer - point).length_squared() < COP_SIGHT_RADIUS_SQUARE),
+        }
+    }
+
+    // Where the entity was last seen. That is where it is now if it is in sight
+    pub fn last_seen(&self, index: usize) -> Option<Vector2> {
+        self.last_seen.get(index).and_then(|position| *position)
+    }
+
+    // Work out what can be seen now that everyone has moved
+    pub fn update(&mut self, state: &State) {
+        for visible in &mut self.visible {
+            *visible = false;
+        }
+        self.viewers.clear();
+
+        let building_grid = &state.visibility_graph.building_grid;
+        for entity in &state.entities {
+            match entity.behaviour {
+                Behaviour::Cop { .. } if entity.sheltering_in.is_none() => (),
+                _ => continue,
+            }
+            self.viewers.push(entity.position);
+
+            // Check each cell in the box around the sight radius that no one else sees
+            let cells_across = (COP_SIGHT_RADIUS / FOG_CELL_SIZE).ceil() as isize;
+            let offset = (entity.position - self.origin) / FOG_CELL_SIZE;
+            let (column, row) = (offset.x.floor() as isize, offset.y.floor() as isize);
+            for j in (row - cells_across).max(0)..(row + cells_across + 1).min(self.rows as isize) {
+                for i in (column - cells_across).max(0)..(column + cells_across + 1).min(self.columns as isize) {
+                    let cell = j as usize * self.columns + i as usize;
+                    if self.visible[cell] || self.blocked[cell] {
+                        continue;
+                    }
+
+                    let center = self.cell_center(cell);
+                    if (center - entity.position).length_squared() < COP_SIGHT_RADIUS_SQUARE &&
+                        can_see(&state.buildings, building_grid, entity.position, center) {
+                        self.visible[cell] = true;
+                    }
+                }
+            }
+        }
+
+        // Forget sightings where anyone can now see that the entity is no longer there
+        self.last_seen.resize(state.entities.len(), None);
+        for (i, entity) in state.entities.iter().enumerate() {
+            if self.is_visible(entity.position) {
+                self.last_seen[i] = Some(entity.position);
+            } else if self.last_seen[i].map_or(false, |position| self.is_visible(position)) {
+                self.last_seen[i] = None;
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::f64::consts::PI;
+
+use crate::core::vector::*;
+use crate::core::scalar::*;
+use crate::simulation::state::*;
+use crate::presentation::display::SpriteType;
+
+// Distance walked over one full swing of the hands, forward and back again
+const STRIDE_LENGTH: Scalar = 1.6;
+
+// Furthest the hands swing forward or back of the shoulders, reached at full speed
+const HAND_SWING: Scalar = 0.15;
+const FULL_SWING_SPEED: Scalar = 1.5;
+
+const TORSO_RADIUS: Scalar = 0.5;
+const HAND_RADIUS: Scalar = 0.15;
+
+// How often a reloading cop's hand works at the gun each second
+const RELOAD_MOTIONS_PER_SECOND: Scalar = 2.0;
+
+// Where each entity is in its walk cycle, from 0 to 1, at the same index as the entity
+#[derive(Clone)]
+pub struct Animations {
+    pub walk_phases: Vec<Scalar>,
+}
+
+impl Animations {
+    pub fn new() -> Animations {
+        Animations {
+            walk_phases: vec!(),
+        }
+    }
+
+    // Move each walk cycle along by how far the entity went, so that the hands swing
+    // in step with its feet
+    pub fn update(&mut self, state: &State, dt: Scalar) {
+        self.walk_phases.resize(state.entities.len(), 0.0);
+        for (phase, entity) in self.walk_phases.iter_mut().zip(&state.entities) {
+            *phase = (*phase + entity.velocity.length() * dt / STRIDE_LENGTH) % 1.0;
+        }
+    }
+
+    pub fn walk_phase(&self, index: usize) -> Scalar {
+        self.walk_phases.get(index).cloned().unwrap_or(0.0)
+    }
+}
+
+// One sprite of an entity. The offset is from the entity's position, with x to its right
+// and y ahead of it, and the angle turns the sprite anticlockwise from the way the entity faces
+pub struct BodyPart {
+    pub sprite_type: SpriteType,
+    pub offset: Vector2,
+    pub angle: Scalar,
+    pub radius: Scalar,
+}
+
+fn body_part(sprite_type: SpriteType, offset: Vector2, radius: Scalar) -> BodyPart {
+    BodyPart { sprite_type, offset, angle: 0.0, radius }
+}
+
+// The sprites making up the entity in its current pose, in no particular order
+pub fn body_parts(entity: &Entity, walk_phase: Scalar) -> Vec<BodyPart> {
+    // Forward for the left hand, and back by as much for the right
+    let speed = entity.velocity.length();
+    let swing = HAND_SWING * (speed / FULL_SWING_SPEED).min(1.0) * (2.0 * PI * walk_phase).sin();
+
+    match &entity.behaviour {
+        Behaviour::Cop { state_stack, .. } => cop_pose(state_stack.last(), swing),
+        Behaviour::Human { .. } => vec![
+            body_part(SpriteType::CivilianHandLeft, vector2(-0.34, -0.2 + swing), HAND_RADIUS),
+            body_part(SpriteType::CivilianHandRight, vector2(0.34, -0.2 - swing), HAND_RADIUS),
+            body_part(SpriteType::CivilianTorso, Vector2::zero(), TORSO_RADIUS),
+        ],
+        Behaviour::Zombie { state } => {
+            // Arms out in front, reaching for whoever is closest, except when dazed
+            let reach = match state {
+                ZombieState::Stunned { .. } => 0.0,
+                _ => 0.35,
+            };
+            vec![
+                body_part(SpriteType::ZombieClawLeft, vector2(-0.3, reach + swing), HAND_RADIUS),
+                body_part(SpriteType::ZombieClawRight, vector2(0.3, reach - swing), HAND_RADIUS),
+                body_part(SpriteType::ZombieTorso, Vector2::zero(), TORSO_RADIUS),
+            ]
+        }
+        Behaviour::Dead => vec![body_part(SpriteType::Dead, Vector2::zero(), TORSO_RADIUS)],
+    }
+}
+
+fn cop_pose(cop_state: Option<&CopState>, swing: Scalar) -> Vec<BodyPart> {
+    let hands = match cop_state {
+        // Gun held out in both hands
+        Some(CopState::Aiming { .. }) => vec![
+            body_part(SpriteType::CopLeftHand, vector2(-0.08, 0.45), HAND_RADIUS),
+            body_part(SpriteType::CopGunHand, vector2(0.02, 0.55), HAND_RADIUS),
+        ],
+        // Gun drawn in close, with the other hand working at it
+        Some(CopState::Reloading { reload_time_remaining }) => {
+            let motion = 0.06 * (2.0 * PI * RELOAD_MOTIONS_PER_SECOND * *reload_time_remaining).sin();
+            vec![
+                body_part(SpriteType::CopLeftHand, vector2(-0.05, 0.25 + motion), HAND_RADIUS),
+                body_part(SpriteType::CopGunHand, vector2(0.15, 0.3), HAND_RADIUS),
+            ]
+        }
+        // Baton brought down across the body on each swing, then raised again
+        Some(CopState::Melee { swing_cooldown_remaining, .. }) => {
+            let follow_through = (*swing_cooldown_remaining / COP_MELEE_SWING_COOLDOWN).max(0.0).min(1.0);
+            let raised = vector2(0.45, 0.1);
+            let struck = vector2(-0.05, 0.5);
+            let mut baton_hand = body_part(
+                SpriteType::CopBatonHand,
+                raised + follow_through * (struck - raised),
+                HAND_RADIUS);
+            baton_hand.angle = follow_through * 0.5 * PI;
+            vec![
+                body_part(SpriteType::CopLeftHand, vector2(-0.38, -0.1), HAND_RADIUS),
+                baton_hand,
+            ]
+        }
+        // Walking, or standing about, with the gun down at the side
+        _ => vec![
+            body_part(SpriteType::CopLeftHand, vector2(-0.38, -0.1 + swing), HAND_RADIUS),
+            body_part(SpriteType::CopGunHand, vector2(0.38, -0.1 - swing), HAND_RADIUS),
+        ],
+    };
+
+    let mut parts = hands;
+    parts.push(body_part(SpriteType::CopTorso, Vector2::zero(), TORSO_RADIUS));
+    parts
+}
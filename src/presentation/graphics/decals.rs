@@ -0,0 +1,46 @@
+use crate::simulation::decals::*;
+use crate::simulation::state::State;
+
+// Once this many marks have been left, each new one replaces the oldest
+pub const MAX_DECALS: usize = 1024;
+
+// The marks on the ground, kept in a ring so that long matches don't gather them
+// without end. They are only drawn, so they aren't saved with the match
+#[derive(Clone)]
+pub struct Decals {
+    pub decals: Vec<Decal>,
+    // Where the next mark goes once the ring is full, which is the oldest one
+    pub next: usize,
+}
+
+impl Decals {
+    pub fn new() -> Decals {
+        Decals {
+            decals: vec!(),
+            next: 0,
+        }
+    }
+
+    pub fn add(&mut self, decal: Decal) {
+        if self.decals.len() < MAX_DECALS {
+            self.decals.push(decal);
+        } else {
+            self.decals[self.next] = decal;
+            self.next = (self.next + 1) % MAX_DECALS;
+        }
+    }
+
+    // Collect the marks left over the last tick. Call after every tick, since they are
+    // gone after the next one
+    pub fn update(&mut self, state: &State) {
+        for decal in &state.new_decals {
+            self.add(*decal);
+        }
+    }
+
+    // Oldest first, so newer marks are drawn over older ones
+    pub fn iter(&self) -> impl Iterator<Item=&Decal> {
+        let (newer, older) = self.decals.split_at(self.next);
+        older.iter().chain(newer.iter())
+    }
+}
@@ -1,3 +1,5 @@
 pub mod renderer;
 pub mod sprite;
-pub mod font;
\ No newline at end of file
+pub mod font;
+pub mod animation;
+pub mod decals;
\ No newline at end of file
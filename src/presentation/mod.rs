@@ -3,3 +3,4 @@ pub mod display;
 pub mod graphics;
 pub mod ui;
 pub mod camera;
+pub mod fog_of_war;
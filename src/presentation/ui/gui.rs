@@ -2,9 +2,13 @@ use crate::core::vector::*;
 use crate::simulation::state::State;
 use crate::core::matrix::Mat4;
 use crate::simulation::control::*;
+use crate::simulation::command::Command;
 use crate::core::geo::intersect::rectangle_point::*;
 use crate::simulation::game_state::GameState;
+use crate::simulation::reinforcements::static_map_bounds;
+use crate::presentation::ui::minimap::minimap_to_world;
 use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
 
 use glium_sdl2::SDL2Facade;
 use sdl2::event::Event;
@@ -26,7 +30,10 @@ pub enum GuiType {
     },
     ZombieUI,
     CopUI,
-    CivilianUI
+    CivilianUI,
+    // Overview of the whole map. Clicking it moves the camera there, and right clicking
+    // it sends the selected cops there
+    Minimap
 }
 
 // Implementing a callback with mut parameters is too challenging
@@ -53,6 +60,18 @@ pub enum ActiveWindow {
 }
 pub static mut CURRENT: ActiveWindow = ActiveWindow::Game;
 
+// HUD button for calling in a squad of reinforcements
+pub const REINFORCEMENTS_BUTTON: &str = "Reinforcements";
+
+const SCENARIO_BUTTON_PREFIX: &str = "Scenario: ";
+
+// Main menu button that shows the selected scenario and cycles through them when clicked
+fn scenario_button(scenario_name: &str) -> Gui {
+    let text = format!("{}{}", SCENARIO_BUTTON_PREFIX, scenario_name);
+    let width = 0.045 * text.len() as f64;
+    Gui::new(GuiType::Button { text }, width, 0.09, Vector2 { x: 0.0, y: -0.05 })
+}
+
 #[derive(Clone)]
 pub struct Component {
     pub components: Vec<Gui>,
@@ -71,31 +90,54 @@ impl Component {
         let button2 = GuiType::Button{text: "Retry".to_string()};
         let button3 = GuiType::Button{text: "Main Menu".to_string()};
         let button4 = GuiType::Button{text: "Instruction".to_string()};
-        let button_ui_1 = Gui::new(button1, 0.17, 0.09, Vector2{x: 0.0, y: -0.225});
-        let button_ui_2 = Gui::new(button2, 0.2, 0.09, Vector2{x: 0.0, y: -0.075});
-        let button_ui_3 = Gui::new(button3, 0.37, 0.09, Vector2{x: 0.0, y: 0.075});
-        let button_ui_4 = Gui::new(button4, 0.4, 0.09, Vector2{x: 0.0, y: 0.225});
+        let button5 = GuiType::Button{text: "Load".to_string()};
+        let button6 = GuiType::Button{text: "Save".to_string()};
+        let button_ui_1 = Gui::new(button1, 0.17, 0.09, Vector2{x: 0.0, y: -0.375});
+        let button_ui_2 = Gui::new(button2, 0.2, 0.09, Vector2{x: 0.0, y: -0.225});
+        let button_ui_3 = Gui::new(button3, 0.37, 0.09, Vector2{x: 0.0, y: -0.075});
+        let button_ui_5 = Gui::new(button5, 0.17, 0.09, Vector2{x: 0.0, y: 0.075});
+        let button_ui_6 = Gui::new(button6, 0.17, 0.09, Vector2{x: 0.0, y: 0.225});
+        let button_ui_4 = Gui::new(button4, 0.4, 0.09, Vector2{x: 0.0, y: 0.375});
         let menu_ui = Gui::new(GuiType::Menu{ _window_gui: Box::new(box_ui),
                                                        _buttons_gui: vec![Box::new(button_ui_4),
+                                                                          Box::new(button_ui_6),
+                                                                          Box::new(button_ui_5),
                                                                           Box::new(button_ui_3),
                                                                           Box::new(button_ui_2),
                                                                           Box::new(button_ui_1)],
                                                        text: "Setting".to_string()},
                                0.1, 0.125,
                                Vector2{x: -0.9, y: 0.9});
+        let reinforcements_button = GuiType::Button{text: REINFORCEMENTS_BUTTON.to_string()};
+        let reinforcements_ui = Gui::new(reinforcements_button, 0.5, 0.09, Vector2{x: 0.7, y: -0.9});
+        let minimap_ui = Gui::new(GuiType::Minimap, 0.4, 0.5, Vector2{x: -0.78, y: -0.57});
+
+        Component {
+            components: vec![selected_ui, drag_ui, menu_ui, cop_ui, civilian_ui, zombie_ui, reinforcements_ui, minimap_ui],
+            active_window: ActiveWindow::Game
+        }
+    }
+
+    pub fn init_replay_gui() -> Component {
+        let zombie_ui = Gui::new(GuiType::ZombieUI, 0.1, 0.1, Vector2{x: 0.91, y: 0.92});
+        let cop_ui = Gui::new(GuiType::CopUI, 0.1, 0.1, Vector2{x: 0.61, y: 0.92});
+        let civilian_ui = Gui::new(GuiType::CivilianUI, 0.1, 0.1, Vector2{x: 0.76, y: 0.92});
 
         Component {
-            components: vec![selected_ui, drag_ui, menu_ui, cop_ui, civilian_ui, zombie_ui],
+            components: vec![cop_ui, civilian_ui, zombie_ui],
             active_window: ActiveWindow::Game
         }
     }
 
-    pub fn init_main_menu_gui() -> Component {
+    pub fn init_main_menu_gui(scenario_name: &str) -> Component {
         // main menu buttons
+        let button_scenario_ui = scenario_button(scenario_name);
         let button_start = GuiType::Button{text: "Start".to_string()};
+        let button_replay = GuiType::Button{text: "Replay".to_string()};
         let button_exit = GuiType::Button {text: "Exit".to_string()};
         let button_start_ui = Gui::new(button_start, 0.25, 0.09, Vector2{x:0.0, y: -0.2});
-        let button_exit_ui = Gui::new(button_exit, 0.18, 0.09, Vector2{x: 0.0, y: -0.5});
+        let button_replay_ui = Gui::new(button_replay, 0.27, 0.09, Vector2{x:0.0, y: -0.35});
+        let button_exit_ui = Gui::new(button_exit, 0.18, 0.09, Vector2{x: 0.0, y: -0.65});
 
         // box containment for main menu settings
         let box_ui = Gui::new(GuiType::Window, 1.8, 1.8, Vector2{x: 0.0, y: 0.0});
@@ -113,15 +155,26 @@ impl Component {
                                                                           Box::new(button_menu_back_ui)],
                                                            text: "Setting".to_string()},
                                     0.35, 0.09,
-                                    Vector2{x: 0.0, y: -0.35});
+                                    Vector2{x: 0.0, y: -0.5});
 
         // component initialization
         Component {
-            components: vec![button_start_ui, button_exit_ui, setting_ui],
+            components: vec![button_scenario_ui, button_start_ui, button_replay_ui, button_exit_ui, setting_ui],
             active_window: ActiveWindow::MainMenu
         }
     }
 
+    // Show a newly selected scenario on the main menu's scenario button
+    pub fn set_scenario_name(&mut self, scenario_name: &str) {
+        for component in &mut self.components {
+            if let GuiType::Button { text } = &component.id {
+                if text.starts_with(SCENARIO_BUTTON_PREFIX) {
+                    *component = scenario_button(scenario_name);
+                }
+            }
+        }
+    }
+
     pub fn init_loss_gui() -> Component {
         let button_retry = GuiType::Button{text: "Retry".to_string()};
         let button_main_menu = GuiType::Button{text: "Main Menu".to_string()};
@@ -149,7 +202,7 @@ impl Component {
     }
 
 
-    pub fn handle_event(&mut self, event: Event, window: &SDL2Facade, camera_frame: Mat4, state: &mut State, game_state: &mut GameState, control: &mut Control) {
+    pub fn handle_event(&mut self, event: Event, window: &SDL2Facade, camera_frame: Mat4, state: &State, game_state: &mut GameState, control: &mut Control, commands: &mut Vec<Command>) {
         // handle events for any menu laid on top of game
         let mut handled_event = false;
         for i in 0..self.components.len() {
@@ -200,6 +253,12 @@ impl Component {
                                             self.active_window = ActiveWindow::Instruction;
                                             handled_event = true;
                                             game_state.game_paused = true;
+                                        } else if display_text == "Save" {
+                                            game_state.save_requested = true;
+                                            handled_event = true;
+                                        } else if display_text == "Load" {
+                                            game_state.load_requested = true;
+                                            handled_event = true;
                                         } else if display_text == "Main Menu" {
                                             game_state.transition_menu = true;
                                         } else if display_text == "Retry" {
@@ -260,12 +319,61 @@ impl Component {
                         }
                     }
                 }
+                GuiType::Button { ref text } if text == REINFORCEMENTS_BUTTON => {
+                    match event {
+                        Event::MouseButtonDown { timestamp: _, window_id: _, which: _, mouse_btn: _, x, y } => {
+                            if self.active_window == ActiveWindow::Game {
+                                let mouse_pos = &mut Vector2 { x: x as f64, y: y as f64 };
+                                translate_mouse_to_camera(mouse_pos, window.window().size());
+
+                                let top_left = Vector2 { x: component.top_left.x, y: component.top_left.y };
+                                let bot_right = Vector2 { x: component.bot_right.x, y: component.bot_right.y };
+                                if check_bounding_box(top_left, bot_right, *mouse_pos) {
+                                    // The next right clicks pick where the squad comes in and rallies
+                                    if state.reinforcements.ready() {
+                                        control.calling_reinforcements = true;
+                                        control.reinforcement_edge = None;
+                                    }
+                                    handled_event = true;
+                                }
+                            }
+                        },
+                        _ => ()
+                    }
+                }
+                GuiType::Minimap if self.active_window == ActiveWindow::Game => {
+                    match event {
+                        Event::MouseButtonDown { timestamp: _, window_id: _, which: _, mouse_btn, x, y } |
+                        Event::MouseButtonUp { timestamp: _, window_id: _, which: _, mouse_btn, x, y } => {
+                            let mouse_pos = &mut Vector2 { x: x as f64, y: y as f64 };
+                            translate_mouse_to_camera(mouse_pos, window.window().size());
+
+                            let top_left = Vector2 { x: component.top_left.x, y: component.top_left.y };
+                            let bot_right = Vector2 { x: component.bot_right.x, y: component.bot_right.y };
+                            // Let a selection box dragged over the minimap finish as usual
+                            if check_bounding_box(top_left, bot_right, *mouse_pos) && !control.mouse_drag {
+                                let (width, height) = window.window().size();
+                                let aspect_ratio = width as f64 / height as f64;
+                                let point = minimap_to_world(component, static_map_bounds(state), aspect_ratio, *mouse_pos);
+                                match (&event, mouse_btn) {
+                                    (Event::MouseButtonDown { .. }, MouseButton::Left) =>
+                                        control.camera_focus = Some(point),
+                                    (Event::MouseButtonUp { .. }, MouseButton::Right) =>
+                                        control.issue_police_order_at(PoliceOrder::Move, state, point, commands),
+                                    _ => ()
+                                }
+                                handled_event = true;
+                            }
+                        },
+                        _ => ()
+                    }
+                }
                 _ => ()
             }
         }
 
         if !handled_event && self.active_window == ActiveWindow::Game {
-            control.handle_event(event, &window, camera_frame, state, game_state);
+            control.handle_event(event, &window, camera_frame, state, game_state, commands);
         }
     }
 
@@ -286,8 +394,12 @@ impl Component {
                                 let check_within_bound = check_bounding_box(top_left, bot_right, *mouse_pos);
                                 if check_within_bound {
                                     let display_text = text;
-                                    if display_text == "Start" {
+                                    if display_text.starts_with(SCENARIO_BUTTON_PREFIX) {
+                                        game_state.cycle_scenario = true;
+                                    } else if display_text == "Start" {
                                         game_state.transition_game = true;
+                                    } else if display_text == "Replay" {
+                                        game_state.transition_replay = true;
                                     } else if display_text == "Exit" {
                                         game_state.terminate = true;
                                     }
@@ -0,0 +1,44 @@
+use crate::core::vector::*;
+use crate::core::scalar::*;
+
+use super::gui::Gui;
+
+// The minimap fits the whole map, given by the corners of its bounding box, into the
+// widget at the same scale across as up, centered where it is narrower than the widget.
+// Points on the widget are in screen coordinates, which are stretched by the window's
+// aspect ratio, its width over its height
+
+// Where the point on the map is shown on the minimap
+pub fn world_to_minimap(minimap: &Gui, bounds: (Vector2, Vector2), aspect_ratio: Scalar, point: Vector2) -> Vector2 {
+    let (origin, scale) = minimap_transform(minimap, bounds, aspect_ratio);
+    let offset = point - bounds.0;
+    origin + vector2(scale.x * offset.x, scale.y * offset.y)
+}
+
+// The point on the map shown at the point on the minimap
+pub fn minimap_to_world(minimap: &Gui, bounds: (Vector2, Vector2), aspect_ratio: Scalar, point: Vector2) -> Vector2 {
+    let (origin, scale) = minimap_transform(minimap, bounds, aspect_ratio);
+    let offset = point - origin;
+    bounds.0 + vector2(offset.x / scale.x, offset.y / scale.y)
+}
+
+// Whether the point on the screen is on the minimap
+pub fn minimap_contains(minimap: &Gui, point: Vector2) -> bool {
+    point.x >= minimap.bot_left.x && point.x <= minimap.bot_right.x &&
+        point.y >= minimap.bot_left.y && point.y <= minimap.top_left.y
+}
+
+// Where the bottom left corner of the map's bounding box is shown, and how much smaller
+// the map is shown across and up
+fn minimap_transform(minimap: &Gui, bounds: (Vector2, Vector2), aspect_ratio: Scalar) -> (Vector2, Vector2) {
+    let (min, max) = bounds;
+    // Never zero, so that an empty map doesn't divide by zero
+    let size = vector2((max.x - min.x).max(1.0), (max.y - min.y).max(1.0));
+    let widget_size = vector2(minimap.bot_right.x - minimap.bot_left.x, minimap.top_left.y - minimap.bot_left.y);
+
+    // A unit of screen height is aspect_ratio times shorter than a unit of width
+    let scale_x = (widget_size.x / size.x).min(widget_size.y / (aspect_ratio * size.y));
+    let scale = vector2(scale_x, aspect_ratio * scale_x);
+    let shown_size = vector2(scale.x * size.x, scale.y * size.y);
+    (minimap.bot_left + 0.5 * (widget_size - shown_size), scale)
+}
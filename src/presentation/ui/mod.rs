@@ -1,2 +1,3 @@
 pub mod glium_text;
-pub mod gui;
\ No newline at end of file
+pub mod gui;
+pub mod minimap;
\ No newline at end of file
@@ -1,7 +1,14 @@
-use crate::simulation::state::State;
-use crate::presentation::ui::gui::Component;
+use crate::core::vector::*;
+use crate::simulation::state::*;
+use crate::simulation::command::Command;
+use crate::simulation::replay::{Replay, LATEST_REPLAY_PATH};
+use crate::simulation::scenario::Scenario;
+use crate::simulation::outcome::{check_outcome, Outcome};
+use crate::presentation::ui::gui::{Component, ActiveWindow};
 use crate::presentation::camera::Camera;
 use crate::simulation::control::Control;
+use crate::presentation::fog_of_war::FogOfWar;
+use crate::simulation::timestep::FixedTimestep;
 
 use crate::{presentation};
 
@@ -17,32 +24,92 @@ use crate::simulation::game_state::GameState;
 use crate::simulation;
 use crate::scenes::scene::*;
 use crate::scenes::main_menu;
+use crate::scenes::save_game;
 use crate::presentation::graphics::font::FontPkg;
+use crate::presentation::graphics::animation::Animations;
+use crate::presentation::graphics::decals::Decals;
 use crate::scenes::victory_screen::VictoryScreen;
 use crate::scenes::loss_screen::LossScreen;
 
 #[derive(Clone)]
 pub struct Game {
     pub state: State,
+    // Where each entity was as of the previous tick, used to interpolate their positions
+    // when rendering
+    pub previous_positions: Vec<Vector2>,
+    pub timestep: FixedTimestep,
     pub gui: Component,
     pub control: Control,
     pub camera: Camera,
     pub game_state: GameState,
+    // Recording of the match so far
+    pub replay: Replay,
+    // What the player's side can see, updated after each batch of ticks
+    pub fog_of_war: FogOfWar,
+    pub animations: Animations,
+    // Blood and scorch marks left so far. Not saved, so loading a game clears them
+    pub decals: Decals,
 }
 
 impl Game {
-    pub fn new() -> Game {
-        let state = simulation::initial_state::initial_state(100, rand::random::<u32>());
+    pub fn new(scenario: Scenario) -> Game {
+        let replay = Replay::new(scenario, rand::random::<u32>());
+        let state = replay.initial_state();
         let gui = presentation::ui::gui::Component::init_game_gui();
         let camera = presentation::camera::Camera::new();
         let control = simulation::control::Control::new();
         let game_state = simulation::game_state::GameState::new();
+        let fog_of_war = FogOfWar::new(&state);
         Game {
+            previous_positions: state.entity_positions(),
+            timestep: FixedTimestep::new(),
             state: state,
             gui: gui,
             control: control,
             camera: camera,
-            game_state: game_state
+            game_state: game_state,
+            replay: replay,
+            fog_of_war: fog_of_war,
+            animations: Animations::new(),
+            decals: Decals::new()
+        }
+    }
+
+    // Apply a player command to the simulation, recording it for the replay
+    fn apply_command(&mut self, command: Command) {
+        simulation::command::apply_command(&mut self.state, &command);
+        self.replay.record(self.state.tick, command);
+    }
+
+    fn save_replay(&mut self) {
+        self.replay.length = self.state.tick;
+        match self.replay.save_to_file(LATEST_REPLAY_PATH) {
+            Ok(()) => println!("Saved replay to {}", LATEST_REPLAY_PATH),
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+
+    fn save(&self) {
+        match save_game::save_to_file(save_game::QUICKSAVE_PATH, &self.state, &self.game_state, &self.camera, &self.replay) {
+            Ok(()) => println!("Saved game to {}", save_game::QUICKSAVE_PATH),
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+
+    fn load(&mut self) {
+        match save_game::load_from_file(save_game::QUICKSAVE_PATH) {
+            Ok(save) => {
+                self.previous_positions = save.state.entity_positions();
+                self.state = save.state;
+                self.game_state = save.game_state;
+                self.camera = save.camera;
+                self.replay = save.replay;
+                self.timestep = FixedTimestep::new();
+                self.fog_of_war = FogOfWar::new(&self.state);
+                self.decals = Decals::new();
+                println!("Loaded game from {}", save_game::QUICKSAVE_PATH);
+            }
+            Err(err) => eprintln!("{}", err),
         }
     }
 }
@@ -56,17 +123,22 @@ impl Scene for Game {
         match self.game_state {
             GameState{terminate, transition_menu, transition_game, zombies_win, humans_win, ..} =>
                 {
-                    if terminate {return UpdateResult::Exit}
+                    if terminate {
+                        self.save_replay();
+                        return UpdateResult::Exit}
                     if transition_game {self.game_state.transition_game = false;
-                        return UpdateResult::Transition(Box::new(Game::new()))}
+                        self.save_replay();
+                        return UpdateResult::Transition(Box::new(Game::new(self.replay.scenario.clone())))}
                     if transition_menu {self.game_state.transition_menu = false;
+                        self.save_replay();
                         return UpdateResult::Transition(Box::new(main_menu::MainMenu::new()))}
                     if zombies_win {
                         self.game_state.tick += 1;
                         // wait 2 seconds
                         if self.game_state.tick == 120 {
                             self.game_state.zombies_win = false;
-                            return UpdateResult::Transition(Box::new(LossScreen::new(self.clone().state)))
+                            self.save_replay();
+                            return UpdateResult::Transition(Box::new(LossScreen::new(self.clone().state, self.replay.scenario.clone())))
                         }
                     }
                     if humans_win {
@@ -74,11 +146,20 @@ impl Scene for Game {
                         // wait 2 seconds
                         if self.game_state.tick == 120 {
                             self.game_state.humans_win = false;
-                            return UpdateResult::Transition(Box::new(VictoryScreen::new(self.clone().state)))
+                            self.save_replay();
+                            return UpdateResult::Transition(Box::new(VictoryScreen::new(self.clone().state, self.replay.scenario.clone())))
                         }
                     }
                 }
         }
+        if self.game_state.save_requested {
+            self.game_state.save_requested = false;
+            self.save();
+        }
+        if self.game_state.load_requested {
+            self.game_state.load_requested = false;
+            self.load();
+        }
         let keyboard_state = event_pump.keyboard_state();
         let mouse_state = event_pump.mouse_state();
         self.camera.update(&keyboard_state, &mouse_state, &window, self.camera.compute_matrix(), delta_time);
@@ -87,6 +168,7 @@ impl Scene for Game {
             match event {
                 // Exit window if escape key pressed or quit event triggered
                 Event::Quit { .. } => {
+                    self.save_replay();
                     return UpdateResult::Exit
                 },
                 Event::KeyDown { keycode: Some(Keycode::L), .. } => {
@@ -96,6 +178,18 @@ impl Scene for Game {
                     println!("  Entity count:     {:?}", self.state.entities.len());
                     println!("  Projectile count: {:?}", self.state.projectiles.len());
                 },
+                // Debugging purposes
+                Event::KeyDown { keycode: Some(Keycode::F1), .. } if self.gui.active_window == ActiveWindow::Game => {
+                    self.apply_command(Command::DebugZombiesWin);
+                    self.game_state.zombies_win = true;
+                },
+                Event::KeyDown { keycode: Some(Keycode::F2), .. } if self.gui.active_window == ActiveWindow::Game => {
+                    self.apply_command(Command::DebugHumansWin);
+                    self.game_state.humans_win = true;
+                },
+                Event::KeyDown { keycode: Some(Keycode::F3), .. } if self.gui.active_window == ActiveWindow::Game => {
+                    self.game_state.fog_of_war_disabled = !self.game_state.fog_of_war_disabled;
+                },
                 Event::MouseWheel {timestamp: _, window_id: _, which: _, x: _, y, direction: _} => {
                     self.camera.cursor_zoom(&mouse_state, y, &window, self.camera.compute_matrix());
                 },
@@ -104,18 +198,44 @@ impl Scene for Game {
                 },
 
                 _ => {
+                    let mut commands = vec!();
                     self.gui.handle_event(event, &window, self.camera.compute_matrix(),
-                                                            &mut self.state, &mut self.game_state,
-                                                            &mut self.control);
+                                                            &self.state, &mut self.game_state,
+                                                            &mut self.control, &mut commands);
+                    for command in commands {
+                        self.apply_command(command);
+                    }
+                    if let Some(point) = self.control.camera_focus.take() {
+                        self.camera.center_on(point);
+                    }
                 }
             }
         }
 
         if !self.game_state.game_paused {
-            let sounds = simulation::update::update(
-                &simulation::update::UpdateArgs { dt: delta_time },
-                &mut self.state);
+            let mut sounds = vec!();
+            for _ in 0..self.timestep.advance(delta_time) {
+                self.previous_positions = self.state.entity_positions();
+                sounds.append(&mut simulation::update::update(
+                    &simulation::update::UpdateArgs { dt: simulation::timestep::TIMESTEP },
+                    &mut self.state));
+                self.decals.update(&self.state);
+            }
+            self.fog_of_war.update(&self.state);
+            self.animations.update(&self.state, delta_time);
             presentation::audio::sound_effects::play_sounds(&sounds);
+
+            // End the match once one of the scenario's win conditions is met
+            if !self.game_state.humans_win && !self.game_state.zombies_win {
+                match check_outcome(&self.state) {
+                    Some(Outcome::HumansWin) => {
+                        self.game_state.humans_win = true;
+                        self.state.statistics.time_to_victory = Some(self.state.time);
+                    }
+                    Some(Outcome::ZombiesWin) => self.game_state.zombies_win = true,
+                    None => (),
+                }
+            }
         }
         UpdateResult::Continue
     }
@@ -134,9 +254,14 @@ impl Scene for Game {
                                        &textures,
                                        &params,
                                        &self.state,
+                                       &self.previous_positions,
+                                       self.timestep.alpha(),
                                        self.camera.compute_matrix(),
                                        &mut self.gui, &fonts,
-                                       &self.control);
+                                       &self.control,
+                                       if self.game_state.fog_of_war_disabled { None } else { Some(&self.fog_of_war) },
+                                       &self.animations,
+                                       &self.decals);
         target.finish().unwrap();
     }
 }
@@ -0,0 +1,64 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Serialize, Deserialize};
+
+use crate::core::scalar::Scalar;
+
+pub const HIGH_SCORES_PATH: &str = "saves/high_scores.ron";
+
+// Only the best scores are kept
+pub const MAX_HIGH_SCORES: usize = 10;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HighScore {
+    pub score: i64,
+    pub scenario: String,
+    pub won: bool,
+    // Length of the match in seconds
+    pub time: Scalar,
+}
+
+// Best scores first. A missing file is an empty table, since nothing has been scored yet
+pub fn load_high_scores(path: &str) -> Result<Vec<HighScore>, String> {
+    if !Path::new(path).exists() {
+        return Ok(vec!());
+    }
+
+    let contents = fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read {}: {}", path, err))?;
+
+    ron::de::from_str(&contents)
+        .map_err(|err| format!("Failed to parse {}: {}", path, err))
+}
+
+pub fn save_high_scores(path: &str, high_scores: &Vec<HighScore>) -> Result<(), String> {
+    let contents = ron::ser::to_string_pretty(high_scores, ron::ser::PrettyConfig::default())
+        .map_err(|err| format!("Failed to serialize high scores: {}", err))?;
+
+    if let Some(dir) = Path::new(path).parent() {
+        fs::create_dir_all(dir)
+            .map_err(|err| format!("Failed to create {}: {}", dir.display(), err))?;
+    }
+
+    fs::write(path, contents)
+        .map_err(|err| format!("Failed to write {}: {}", path, err))
+}
+
+// Add the score to the table on disk, if it is good enough, and return the new table
+pub fn record_high_score(path: &str, high_score: HighScore) -> Result<Vec<HighScore>, String> {
+    let mut high_scores = load_high_scores(path)?;
+
+    // Ties go to the earlier score
+    let position = high_scores.iter()
+        .position(|existing| existing.score < high_score.score)
+        .unwrap_or(high_scores.len());
+    if position >= MAX_HIGH_SCORES {
+        return Ok(high_scores);
+    }
+    high_scores.insert(position, high_score);
+    high_scores.truncate(MAX_HIGH_SCORES);
+
+    save_high_scores(path, &high_scores)?;
+    Ok(high_scores)
+}
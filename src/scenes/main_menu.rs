@@ -8,20 +8,35 @@ use crate::{presentation, simulation};
 use sdl2::keyboard::Keycode;
 use crate::simulation::game_state::GameState;
 use crate::scenes::game;
+use crate::scenes::replay_viewer::ReplayViewer;
+use crate::simulation::replay::{Replay, LATEST_REPLAY_PATH};
+use crate::simulation::scenario::{Scenario, load_scenarios, SCENARIO_DIRECTORY};
 use crate::presentation::graphics::font::FontPkg;
 
 pub struct MainMenu {
     gui: Component,
     game_state: GameState,
+    // Scenarios the player can pick from, starting with a freshly generated city
+    scenarios: Vec<Scenario>,
+    selected_scenario: usize,
 }
 
+const GENERATED_ENTITY_COUNT: u32 = 100;
+
 impl MainMenu {
     pub fn new() -> MainMenu {
-        let gui = presentation::ui::gui::Component::init_main_menu_gui();
+        let mut scenarios = vec![
+            Scenario::generated_city(GENERATED_ENTITY_COUNT, rand::random::<u32>()),
+            Scenario::generated(GENERATED_ENTITY_COUNT),
+        ];
+        scenarios.append(&mut load_scenarios(SCENARIO_DIRECTORY));
+        let gui = presentation::ui::gui::Component::init_main_menu_gui(&scenarios[0].name);
         let game_state = simulation::game_state::GameState::new();
         MainMenu {
             gui: gui,
-            game_state: game_state
+            game_state: game_state,
+            scenarios: scenarios,
+            selected_scenario: 0,
         }
     }
 }
@@ -33,10 +48,21 @@ impl Scene for MainMenu {
               delta_time: f64)
               -> UpdateResult {
         match self.game_state {
-            GameState{transition_game, terminate, ..} =>
+            GameState{transition_game, transition_replay, cycle_scenario, terminate, ..} =>
                 {
+                    if cycle_scenario {self.game_state.cycle_scenario = false;
+                        self.selected_scenario = (self.selected_scenario + 1) % self.scenarios.len();
+                        self.gui.set_scenario_name(&self.scenarios[self.selected_scenario].name);
+                    }
                     if transition_game {self.game_state.transition_game = false;
-                        return UpdateResult::Transition(Box::new(game::Game::new()))}
+                        let scenario = self.scenarios[self.selected_scenario].clone();
+                        return UpdateResult::Transition(Box::new(game::Game::new(scenario)))}
+                    if transition_replay {self.game_state.transition_replay = false;
+                        match Replay::load_from_file(LATEST_REPLAY_PATH) {
+                            Ok(replay) => return UpdateResult::Transition(Box::new(ReplayViewer::new(replay))),
+                            Err(err) => eprintln!("{}", err),
+                        }
+                    }
                     if terminate {return UpdateResult::Exit}
                 }
         }
@@ -2,4 +2,7 @@ pub mod scene;
 pub mod game;
 pub mod main_menu;
 pub mod victory_screen;
-pub mod loss_screen;
\ No newline at end of file
+pub mod loss_screen;
+pub mod save_game;
+pub mod replay_viewer;
+pub mod high_scores;
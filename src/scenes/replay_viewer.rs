@@ -0,0 +1,165 @@
+use crate::core::scalar::Scalar;
+use crate::core::vector::*;
+use crate::simulation::replay::{Replay, ReplayPlayer};
+use crate::simulation::timestep::*;
+use crate::simulation::update::UpdateArgs;
+use crate::simulation::control::Control;
+use crate::presentation::ui::gui::Component;
+use crate::presentation::camera::Camera;
+use crate::presentation::display::{Programs, Textures};
+use crate::presentation::graphics::font::FontPkg;
+use crate::presentation::graphics::animation::Animations;
+use crate::presentation::graphics::decals::Decals;
+use crate::scenes::scene::{Scene, UpdateResult};
+use crate::scenes::main_menu;
+use crate::presentation;
+
+use glium_sdl2::SDL2Facade;
+use glium::DrawParameters;
+use sdl2::EventPump;
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
+
+// How far the arrow keys seek, in seconds of simulation time
+const SEEK_SECONDS: Scalar = 10.0;
+
+const MIN_SPEED: Scalar = 0.25;
+const MAX_SPEED: Scalar = 16.0;
+
+pub struct ReplayViewer {
+    player: ReplayPlayer,
+    // Where each entity was as of the previous tick, used to interpolate their positions
+    // when rendering
+    previous_positions: Vec<Vector2>,
+    timestep: FixedTimestep,
+    camera: Camera,
+    gui: Component,
+    control: Control,
+    paused: bool,
+    speed: Scalar,
+    animations: Animations,
+    decals: Decals,
+}
+
+impl ReplayViewer {
+    pub fn new(replay: Replay) -> ReplayViewer {
+        let player = ReplayPlayer::new(replay);
+        ReplayViewer {
+            previous_positions: player.state.entity_positions(),
+            player: player,
+            // Allow for every tick of a sped up frame, on top of the usual catching up
+            timestep: FixedTimestep::with_max_ticks_per_frame(MAX_SPEED as usize * MAX_TICKS_PER_FRAME),
+            camera: Camera::new(),
+            gui: Component::init_replay_gui(),
+            control: Control::new(),
+            paused: false,
+            speed: 1.0,
+            animations: Animations::new(),
+            decals: Decals::new(),
+        }
+    }
+
+    fn seek_by(&mut self, seconds: Scalar) {
+        let current = self.player.state.tick as Scalar;
+        let target = (current + seconds * TICK_RATE)
+            .max(0.0)
+            .min(self.player.replay.length as Scalar) as u64;
+
+        self.player.seek(target, &UpdateArgs { dt: TIMESTEP });
+        // Marks left along the way are skipped over, rather than replayed
+        self.decals = Decals::new();
+        self.previous_positions = self.player.state.entity_positions();
+    }
+
+    fn status_text(&self) -> String {
+        let elapsed = self.player.state.tick as Scalar / TICK_RATE;
+        let length = self.player.replay.length as Scalar / TICK_RATE;
+        format!("Replay {:.1}s / {:.1}s  x{}{}",
+                elapsed,
+                length,
+                self.speed,
+                if self.paused { "  (paused)" } else { "" })
+    }
+}
+
+impl Scene for ReplayViewer {
+    fn update(&mut self,
+              event_pump: &mut EventPump,
+              window: &SDL2Facade,
+              delta_time: f64)
+              -> UpdateResult {
+
+        let keyboard_state = event_pump.keyboard_state();
+        let mouse_state = event_pump.mouse_state();
+        self.camera.update(&keyboard_state, &mouse_state, &window, self.camera.compute_matrix(), delta_time);
+        for event in event_pump.poll_iter() {
+            use sdl2::event::Event;
+            match event {
+                // Exit window if quit event triggered
+                Event::Quit { .. } => {
+                    return UpdateResult::Exit
+                },
+                Event::KeyDown { keycode: Some(key), .. } => {
+                    match key {
+                        Keycode::Escape =>
+                            return UpdateResult::Transition(Box::new(main_menu::MainMenu::new())),
+                        Keycode::Space => self.paused = !self.paused,
+                        Keycode::Right => self.seek_by(SEEK_SECONDS),
+                        Keycode::Left => self.seek_by(-SEEK_SECONDS),
+                        Keycode::Up => self.speed = (2.0 * self.speed).min(MAX_SPEED),
+                        Keycode::Down => self.speed = (0.5 * self.speed).max(MIN_SPEED),
+                        _ => ()
+                    }
+                },
+                Event::MouseWheel {timestamp: _, window_id: _, which: _, x: _, y, direction: _} => {
+                    self.camera.cursor_zoom(&mouse_state, y, &window, self.camera.compute_matrix());
+                },
+                Event::MouseButtonDown {timestamp: _, window_id: _, which: _, mouse_btn: MouseButton::Middle , x, y} => {
+                    self.camera.camera_pan(x, y);
+                },
+                _ => ()
+            }
+        }
+
+        if !self.paused {
+            let mut sounds = vec!();
+            for _ in 0..self.timestep.advance(self.speed * delta_time) {
+                if self.player.state.tick >= self.player.replay.length {
+                    break;
+                }
+                self.previous_positions = self.player.state.entity_positions();
+                sounds.append(&mut self.player.step(&UpdateArgs { dt: TIMESTEP }));
+                self.decals.update(&self.player.state);
+            }
+            self.animations.update(&self.player.state, self.speed * delta_time);
+            presentation::audio::sound_effects::play_sounds(&sounds);
+        }
+        UpdateResult::Continue
+    }
+
+    fn render(&mut self,
+              window: &SDL2Facade,
+              programs: &Programs,
+              textures: &Textures,
+              params: &DrawParameters,
+              fonts: &FontPkg) {
+
+        let mut target = window.draw();
+        presentation::display::display(&mut target,
+                                       &window,
+                                       &programs,
+                                       &textures,
+                                       &params,
+                                       &self.player.state,
+                                       &self.previous_positions,
+                                       self.timestep.alpha(),
+                                       self.camera.compute_matrix(),
+                                       &mut self.gui, &fonts,
+                                       &self.control,
+                                       None,
+                                       &self.animations,
+                                       &self.decals);
+        presentation::display::draw_status_text(&mut target, &window, &fonts, &self.status_text());
+        target.finish().unwrap();
+    }
+}
@@ -0,0 +1,79 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Serialize, Deserialize};
+
+use crate::presentation::camera::Camera;
+use crate::simulation::game_state::GameState;
+use crate::simulation::replay::Replay;
+use crate::simulation::state::State;
+
+// Increment whenever the layout of anything written to a save file changes
+pub const SAVE_FORMAT_VERSION: u32 = 1;
+
+pub const QUICKSAVE_PATH: &str = "saves/quicksave.ron";
+
+#[derive(Serialize, Deserialize)]
+pub struct SaveFile {
+    pub version: u32,
+    pub state: State,
+    pub game_state: GameState,
+    pub camera: Camera,
+    // Recording of the match up to the save, so the replay stays complete after loading
+    pub replay: Replay,
+}
+
+// Read on its own first, so that saves from other versions are rejected with a useful message
+// instead of a parse error
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+pub fn save_to_file(
+    path: &str,
+    state: &State,
+    game_state: &GameState,
+    camera: &Camera,
+    replay: &Replay) -> Result<(), String> {
+
+    let save = SaveFile {
+        version: SAVE_FORMAT_VERSION,
+        state: state.clone(),
+        game_state: game_state.clone(),
+        camera: camera.clone(),
+        replay: replay.clone(),
+    };
+
+    let contents = ron::ser::to_string_pretty(&save, ron::ser::PrettyConfig::default())
+        .map_err(|err| format!("Failed to serialize save: {}", err))?;
+
+    if let Some(dir) = Path::new(path).parent() {
+        fs::create_dir_all(dir)
+            .map_err(|err| format!("Failed to create {}: {}", dir.display(), err))?;
+    }
+
+    fs::write(path, contents)
+        .map_err(|err| format!("Failed to write {}: {}", path, err))
+}
+
+pub fn load_from_file(path: &str) -> Result<SaveFile, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read {}: {}", path, err))?;
+
+    let header: SaveHeader = ron::de::from_str(&contents)
+        .map_err(|err| format!("Failed to parse {}: {}", path, err))?;
+
+    if header.version != SAVE_FORMAT_VERSION {
+        return Err(format!("Save {} has version {}, expected version {}",
+                           path, header.version, SAVE_FORMAT_VERSION));
+    }
+
+    let mut save: SaveFile = ron::de::from_str(&contents)
+        .map_err(|err| format!("Failed to parse {}: {}", path, err))?;
+
+    // Navigation data isn't saved, so derive it again from the buildings
+    save.state.rebuild_visibility_graph();
+
+    Ok(save)
+}
@@ -1,5 +1,6 @@
 use crate::simulation::state::State;
 use crate::simulation::game_state::GameState;
+use crate::simulation::scenario::Scenario;
 use crate::presentation::ui::gui::Component;
 use crate::scenes::scene::{Scene, UpdateResult};
 use sdl2::EventPump;
@@ -10,21 +11,45 @@ use crate::presentation::graphics::font::FontPkg;
 use crate::{simulation, presentation};
 use crate::scenes::{game, main_menu};
 use sdl2::keyboard::Keycode;
+use crate::simulation::statistics::{score, Score};
+use crate::scenes::high_scores::*;
 
 pub struct VictoryScreen {
     state: State,
+    // Scenario to play again on retry
+    scenario: Scenario,
     game_state: GameState,
     gui: Component,
+    score: Score,
+    // Best scores so far, including this one if it made the table
+    high_scores: Vec<HighScore>,
 }
 
 impl VictoryScreen {
-    pub fn new(state: State) -> VictoryScreen {
+    pub fn new(state: State, scenario: Scenario) -> VictoryScreen {
         let game_state = simulation::game_state::GameState::new();
         let gui = presentation::ui::gui::Component::init_victory_gui();
+        let score = score(&state);
+        let high_score = HighScore {
+            score: score.total(),
+            scenario: scenario.name.clone(),
+            won: true,
+            time: state.statistics.time_to_victory.unwrap_or(state.time),
+        };
+        let high_scores = match record_high_score(HIGH_SCORES_PATH, high_score) {
+            Ok(high_scores) => high_scores,
+            Err(err) => {
+                eprintln!("{}", err);
+                vec!()
+            }
+        };
         VictoryScreen {
             state: state,
+            scenario: scenario,
             game_state: game_state,
             gui: gui,
+            score: score,
+            high_scores: high_scores,
         }
     }
 }
@@ -39,7 +64,7 @@ impl Scene for VictoryScreen {
             GameState{transition_game, transition_menu, terminate, ..} =>
                 {
                     if transition_game {self.game_state.transition_game = false;
-                        return UpdateResult::Transition(Box::new(game::Game::new()))}
+                        return UpdateResult::Transition(Box::new(game::Game::new(self.scenario.clone())))}
                     if transition_menu {self.game_state.transition_menu = false;
                         return UpdateResult::Transition(Box::new(main_menu::MainMenu::new()))}
                     if terminate {return UpdateResult::Exit}
@@ -80,6 +105,8 @@ impl Scene for VictoryScreen {
                                                    &params,
                                                    &mut self.gui,
                                                    &self.state,
+                                                   &self.score,
+                                                   &self.high_scores,
                                                    &fonts);
         target.finish().unwrap();
     }
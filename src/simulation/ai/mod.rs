@@ -1,2 +1,3 @@
 pub mod pathfinding;
 pub mod path;
+pub mod visibility_graph;
@@ -1,22 +1,23 @@
 use std::cmp::*;
 
 use crate::core::scalar::Scalar;
-use crate::core::vector::Vector2;
+use crate::core::vector::*;
+use serde::{Serialize, Deserialize};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Node {
     pub pos: Vector2,
     pub h: Scalar
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Edge {
     pub start: Node,
     pub end: Node,
     pub cost: Scalar
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Path {
     pub edges: Vec<Edge>,
     pub cost: Scalar
@@ -47,6 +48,19 @@ impl Path {
         self.edges.push(e);
     }
 
+    // Make the path start from pos and head straight for the end of edge i,
+    // dropping the edges before it
+    pub fn start_from(&mut self, pos: Vector2, i: usize) {
+        self.edges.drain(..i);
+
+        let goal = self.edges[self.edges.len() - 1].end.pos;
+        let first = &mut self.edges[0];
+        first.start = Node { pos, h: (goal - pos).length() };
+        first.cost = (first.end.pos - pos).length();
+
+        self.cost = self.edges.iter().map(|edge| edge.cost).sum();
+    }
+
     pub fn f_stat(&self) -> Scalar {
         match self.edges.last() {
             Some(edge) => (edge.end.h + self.cost) as Scalar,
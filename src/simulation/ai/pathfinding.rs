@@ -1,151 +1,149 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
 use crate::core::vector::*;
 use crate::core::scalar::*;
 use crate::simulation::ai::path::{Node, Edge, Path};
+use crate::simulation::ai::visibility_graph::VisibilityGraph;
+use crate::simulation::spatial_grid::SpatialGrid;
 use crate::core::geo::polygon::*;
 
-struct Graph {
-    _start: Node,
-    goal: Node,
-    edges: Vec<Edge>
+// A graph node waiting to be expanded, with its f-stat: the cost to reach it plus the
+// estimated cost from it to the goal
+#[derive(Copy, Clone, PartialEq)]
+struct FrontierEntry {
+    f: Scalar,
+    node: usize,
 }
 
-impl Graph {
-    fn new(start_pos: Vector2, end_pos: Vector2) -> Self {
-        let start_node = Node { pos: start_pos, h: euclidean_dist(start_pos, end_pos) };
-        let end_node = Node { pos: end_pos, h: 0.0 };
+impl Eq for FrontierEntry {}
 
-        Graph {
-            _start: start_node,
-            goal: end_node,
-            edges: Vec::new()
-        }
+impl Ord for FrontierEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed, so that the max-heap pops the entry with the lowest f-stat first
+        other.f.partial_cmp(&self.f).unwrap_or(Ordering::Equal)
     }
+}
 
-    // Add a new edge to the graph, unless it already exists.
-    // Creates new nodes if necessary.
-    // Does not check if edge is disconnected from graph
-    fn add_edge(&mut self, start_pos: Vector2, end_pos: Vector2) -> Edge {
-        match self.edges.iter().find(|&&edge| edge.start.pos == start_pos && edge.end.pos == end_pos) {
-            Some(edge) => *edge,
-            None => {
-                let edge = Edge {
-                    start: Node { pos: start_pos, h: euclidean_dist(start_pos, self.goal.pos) },
-                    end: Node { pos: end_pos, h: euclidean_dist(end_pos, self.goal.pos) },
-                    cost: euclidean_dist(start_pos, end_pos)
-                };
-                self.edges.push(edge);
-
-                edge
-            }
-        }
+impl PartialOrd for FrontierEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
-// Find the shortest path from start_pos to end_pos, accounting for obstacles
+// Find the shortest path from start_pos to end_pos, accounting for the graph's obstacles
 pub fn find_path(
     start_pos: Vector2,
     end_pos: Vector2,
-    obstacles: &Vec<Polygon>,
-    outlines: &Vec<Polygon>) -> Option<Path> {
+    graph: &VisibilityGraph) -> Option<Path> {
 
-    // Initialize the graph representing the viable paths from start to end
-    let mut graph = Graph::new(start_pos, end_pos);
+    // Nothing between start and end, answer is a straight line
+    if graph.has_clear_path(start_pos, end_pos) {
+        return Some(Path::from_edge(make_edge(start_pos, end_pos, end_pos)));
+    }
 
-    // Find all intervening obstacles between the start and the goal and store the position of the
-    // intersect and the index of the obstacle
-    let mut intersections: Vec<(Vector2, usize)> = vec!();
-    for i in 0..obstacles.len() {
-        for intersect in obstacles[i].intersects(start_pos, end_pos) {
-            intersections.push((intersect, i));
+    // The start and goal join the graph's nodes for the duration of the search
+    let node_count = graph.nodes.len();
+    let start = node_count;
+    let goal = node_count + 1;
+    let position = |node: usize| {
+        if node == start { start_pos } else if node == goal { end_pos } else { graph.nodes[node] }
+    };
+
+    let start_neighbours: Vec<(usize, Scalar)> = (0..node_count)
+        .filter(|&i| graph.connects(i, start_pos))
+        .map(|i| (i, euclidean_dist(start_pos, graph.nodes[i])))
+        .collect();
+
+    let mut cost = vec![INFINITY; node_count + 2];
+    let mut came_from: Vec<Option<usize>> = vec![None; node_count + 2];
+    let mut expanded = vec![false; node_count + 2];
+    let mut frontier = BinaryHeap::new();
+
+    cost[start] = 0.0;
+    frontier.push(FrontierEntry { f: euclidean_dist(start_pos, end_pos), node: start });
+
+    while let Some(FrontierEntry { node, .. }) = frontier.pop() {
+        if node == goal {
+            return Some(reconstruct_path(&came_from, goal, &position));
         }
-    }
 
-    // Find the closest intersection point
-    match get_min_index_by_dist(start_pos, intersections) {
-
-        // Nothing between start and end, answer is a straight line
-//        None => return Some(vec![start_pos, end_pos]),
-        None => return Some(Path::from_edge(Edge {
-            start: Node { pos: start_pos, h: euclidean_dist(start_pos, end_pos) },
-            end: Node { pos: end_pos, h: 0.0 },
-            cost: euclidean_dist(start_pos, end_pos)
-        })),
-        Some(i) => {
-            // Initializing the frontier
-            let mut frontier = vec!();
-
-            // Add paths to the frontier based on outline of closest obstacle
-            for j in 0..outlines[i].num_sides() {
-                if obstacles[i].intersects(start_pos, outlines[i].get(j)).len() == 0 {
-                    frontier.push(Path::from_edge(graph.add_edge(start_pos, outlines[i].get(j))));
-                }
-            }
+        // The same node can be pushed several times as cheaper routes to it turn up
+        if expanded[node] {
+            continue;
+        }
+        expanded[node] = true;
 
-            while frontier.len() > 0 {
-                // Sort frontier so that path with lowest f-stat is last (so it can be popped)
-                frontier.sort_unstable_by(|a, b| b.partial_cmp(a).unwrap());
-
-                match frontier.pop() {
-                    Some(mut path) => {
-                        // Check if end of the path is the goal node
-                        match path.edges.last() {
-                            Some(edge) => {
-                                if edge.end.pos == end_pos {
-                                    return Some(path)
-                                }
-
-                                // Find all intersections between current edge and goal
-                                intersections = vec!();
-                                for j in 0..obstacles.len() {
-                                    for intersect in obstacles[j].intersects(edge.end.pos, end_pos) {
-                                        intersections.push((intersect, j));
-                                    }
-                                }
-
-                                match get_min_index_by_dist(edge.end.pos, intersections) {
-                                    None => {
-                                        // No intersections, go straight to the goal
-                                        path.append_edge(graph.add_edge(edge.end.pos, end_pos));
-                                        frontier.push(path);
-                                    }
-                                    Some(j) => {
-                                        // Add paths to the frontier based on outline of closest obstacle
-                                        for k in 0..outlines[j].num_sides() {
-                                            if edge.end.pos != outlines[j].get(k) && obstacles[j].intersects(edge.end.pos, outlines[j].get(k)).len() == 0 {
-                                                let mut new_path = path.clone();
-                                                new_path.append_edge(graph.add_edge(edge.end.pos, outlines[j].get(k)));
-                                                frontier.push(new_path);
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            None => return None
-                        }
-                    }
-                    None => return None
-                }
-            }
+        let neighbours = if node == start { &start_neighbours } else { &graph.neighbours[node] };
+        let goal_neighbour = if node != start && graph.connects(node, end_pos) {
+            Some((goal, euclidean_dist(graph.nodes[node], end_pos)))
+        } else {
             None
+        };
+
+        for &(next, step_cost) in neighbours.iter().chain(goal_neighbour.iter()) {
+            let next_cost = cost[node] + step_cost;
+            if next_cost < cost[next] {
+                cost[next] = next_cost;
+                came_from[next] = Some(node);
+                frontier.push(FrontierEntry {
+                    f: next_cost + euclidean_dist(position(next), end_pos),
+                    node: next,
+                });
+            }
         }
     }
+
+    None
 }
 
-fn get_min_index_by_dist(pos: Vector2, intersections: Vec<(Vector2, usize)>) -> Option<usize> {
+// Check that the straight line between two points doesn't pass through an obstacle
+pub fn can_see(
+    obstacles: &Vec<Polygon>,
+    obstacle_grid: &SpatialGrid,
+    from: Vector2,
+    to: Vector2) -> bool {
+
+    for i in obstacle_grid.query_line(from, to) {
+        let obstacle = &obstacles[i];
+
+        // Skip obstacles whose bounding box doesn't overlap the line of sight
+        let (min, max) = obstacle.bounding_box();
+        if max.x < from.x.min(to.x) || min.x > from.x.max(to.x) ||
+            max.y < from.y.min(to.y) || min.y > from.y.max(to.y) {
+            continue;
+        }
 
-    let mut index = 0;
-    let mut dist = INFINITY;
+        if obstacle.num_intersects(from, to) > 0 {
+            return false;
+        }
+    }
+    true
+}
 
-    if intersections.len() == 0 { return None }
+fn reconstruct_path<F>(came_from: &Vec<Option<usize>>, goal: usize, position: &F) -> Path
+    where F: Fn(usize) -> Vector2 {
 
-    for intersection in intersections {
-        if euclidean_dist(pos, intersection.0) < dist {
-            dist = euclidean_dist(pos, intersection.0);
-            index = intersection.1;
-        }
+    let mut nodes = vec![goal];
+    while let Some(previous) = came_from[*nodes.last().unwrap()] {
+        nodes.push(previous);
     }
+    nodes.reverse();
 
-    Some(index)
+    let end_pos = position(goal);
+    let mut path = Path::from_edge(make_edge(position(nodes[0]), position(nodes[1]), end_pos));
+    for i in 2..nodes.len() {
+        path.append_edge(make_edge(position(nodes[i - 1]), position(nodes[i]), end_pos));
+    }
+    path
+}
+
+fn make_edge(start_pos: Vector2, end_pos: Vector2, goal_pos: Vector2) -> Edge {
+    Edge {
+        start: Node { pos: start_pos, h: euclidean_dist(start_pos, goal_pos) },
+        end: Node { pos: end_pos, h: euclidean_dist(end_pos, goal_pos) },
+        cost: euclidean_dist(start_pos, end_pos)
+    }
 }
 
 fn euclidean_dist(a: Vector2, b: Vector2) -> Scalar {
@@ -0,0 +1,96 @@
+use crate::core::vector::*;
+use crate::core::scalar::*;
+use crate::core::geo::polygon::*;
+use crate::simulation::ai::pathfinding::can_see;
+use crate::simulation::spatial_grid::SpatialGrid;
+
+// Graph of the straight paths between the corners of the obstacle outlines.
+// The shortest path around the obstacles only ever turns at these corners, so
+// pathfinding only has to connect the start and goal to the graph
+pub struct VisibilityGraph {
+    // Everything that blocks movement: the buildings, followed by any other obstacles
+    pub obstacles: Vec<Polygon>,
+    // Broadphase over the obstacles, for checking for a clear path
+    pub obstacle_grid: SpatialGrid,
+    // Broadphase over just the buildings, for checking line of sight. Buildings
+    // don't move, so their grid is kept here with the rest of the navigation data
+    pub building_grid: SpatialGrid,
+    // Outline vertices that aren't inside an obstacle
+    pub nodes: Vec<Vector2>,
+    // The vertices before and after each node on its outline
+    corners: Vec<(Vector2, Vector2)>,
+    // For each node, the nodes it can see and the distance to them
+    pub neighbours: Vec<Vec<(usize, Scalar)>>,
+}
+
+impl VisibilityGraph {
+    pub fn new(buildings: &Vec<Polygon>, obstacles: Vec<Polygon>, outlines: &Vec<Polygon>) -> VisibilityGraph {
+        let mut graph = VisibilityGraph {
+            obstacle_grid: SpatialGrid::from_polygons(&obstacles),
+            building_grid: SpatialGrid::from_polygons(buildings),
+            obstacles,
+            nodes: vec!(),
+            corners: vec!(),
+            neighbours: vec!(),
+        };
+
+        for outline in outlines {
+            let sides = outline.num_sides();
+            for i in 0..sides {
+                let pos = outline.get(i);
+
+                // Outlines of neighbouring buildings can overlap, leaving some corners unreachable
+                if graph.obstacles.iter().any(|obstacle| obstacle.contains_point(pos)) {
+                    continue;
+                }
+
+                graph.nodes.push(pos);
+                graph.corners.push((outline.get((i + sides - 1) % sides), outline.get((i + 1) % sides)));
+            }
+        }
+
+        graph.neighbours = vec![vec!(); graph.nodes.len()];
+        for i in 0..graph.nodes.len() {
+            for j in (i + 1)..graph.nodes.len() {
+                if graph.is_tangent(i, graph.nodes[j]) &&
+                    graph.is_tangent(j, graph.nodes[i]) &&
+                    can_see(&graph.obstacles, &graph.obstacle_grid, graph.nodes[i], graph.nodes[j]) {
+
+                    let cost = (graph.nodes[j] - graph.nodes[i]).length();
+                    graph.neighbours[i].push((j, cost));
+                    graph.neighbours[j].push((i, cost));
+                }
+            }
+        }
+
+        graph
+    }
+
+    // Check whether a straight path between the node and a point is worth considering
+    pub fn connects(&self, node: usize, pos: Vector2) -> bool {
+        self.is_tangent(node, pos) && self.has_clear_path(self.nodes[node], pos)
+    }
+
+    // Check that nothing blocks the straight path between two points
+    pub fn has_clear_path(&self, from: Vector2, to: Vector2) -> bool {
+        can_see(&self.obstacles, &self.obstacle_grid, from, to)
+    }
+
+    // A shortest path only turns at a corner to go around it, so it must touch the
+    // outline there without cutting into it. That is, the corner's neighbouring
+    // vertices must both lie on the same side of the path
+    fn is_tangent(&self, node: usize, pos: Vector2) -> bool {
+        let direction = pos - self.nodes[node];
+        let (previous, next) = self.corners[node];
+        let side_of_previous = direction.cross(previous - self.nodes[node]);
+        let side_of_next = direction.cross(next - self.nodes[node]);
+
+        side_of_previous * side_of_next >= 0.0
+    }
+}
+
+impl Default for VisibilityGraph {
+    fn default() -> VisibilityGraph {
+        VisibilityGraph::new(&vec!(), vec!(), &vec!())
+    }
+}
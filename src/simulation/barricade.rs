@@ -0,0 +1,95 @@
+use serde::{Serialize, Deserialize};
+
+use crate::core::vector::*;
+use crate::core::scalar::*;
+use crate::core::geo::polygon::*;
+use crate::core::geo::segment2::*;
+
+use super::state::*;
+
+pub const BARRICADE_THICKNESS: Scalar = 0.6;
+
+// Longer barricades are cut short at this length, measured from where they start,
+// and shorter ones aren't built at all
+pub const BARRICADE_MAX_LENGTH: Scalar = 12.0;
+pub const BARRICADE_MIN_LENGTH: Scalar = 1.0;
+
+pub const BARRICADE_MAX_HEALTH: Scalar = 200.0;
+
+// Seconds of work it takes one cop to build each unit of length. Cops building
+// the same barricade together share the work
+pub const BARRICADE_BUILD_TIME_PER_LENGTH: Scalar = 1.5;
+
+// How close to a barricade, centre to centre, a cop has to be to work on it
+pub const COP_BUILD_REACH: Scalar = 1.5;
+
+// Damage a zombie does to a barricade each second it spends pushing against it
+pub const ZOMBIE_BARRICADE_DAMAGE_PER_SECOND: Scalar = 10.0;
+
+// A barricade built by the cops between two points. It blocks movement but is low
+// enough to see and shoot over
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Barricade {
+    pub start: Vector2,
+    pub end: Vector2,
+    // The barricade is destroyed on reaching zero
+    pub health: Scalar,
+    // Seconds of work left until it is finished. It doesn't block anything until then
+    pub build_time_remaining: Scalar,
+}
+
+impl Barricade {
+    pub fn new(start: Vector2, end: Vector2) -> Barricade {
+        let delta = end - start;
+        let end = if delta.length() > BARRICADE_MAX_LENGTH {
+            start + BARRICADE_MAX_LENGTH * delta.normalize()
+        } else {
+            end
+        };
+
+        Barricade {
+            start,
+            end,
+            health: BARRICADE_MAX_HEALTH,
+            build_time_remaining: BARRICADE_BUILD_TIME_PER_LENGTH * (end - start).length(),
+        }
+    }
+
+    pub fn is_built(&self) -> bool {
+        self.build_time_remaining <= 0.0
+    }
+
+    pub fn is_destroyed(&self) -> bool {
+        self.health <= 0.0
+    }
+
+    // Finished and still in one piece
+    pub fn is_standing(&self) -> bool {
+        self.is_built() && !self.is_destroyed()
+    }
+
+    pub fn segment(&self) -> Segment2 {
+        Segment2 { p1: self.start, p2: self.end }
+    }
+
+    // The footprint of the barricade, as an obstacle like a building
+    pub fn polygon(&self) -> Polygon {
+        let across = 0.5 * BARRICADE_THICKNESS * (self.end - self.start).normalize().right();
+        Polygon(vec![
+            self.start - across,
+            self.end - across,
+            self.end + across,
+            self.start + across,
+        ])
+    }
+
+    // Where a cop at the position should stand to work on the barricade: next to the
+    // nearest point along it, on the cop's side
+    pub fn work_position(&self, position: Vector2) -> Vector2 {
+        let nearest = self.segment().nearest_point_to_point(position);
+        let across = (self.end - self.start).normalize().right();
+        let side = if across.dot(position - nearest) < 0.0 { -1.0 } else { 1.0 };
+
+        nearest + side * (0.5 * BARRICADE_THICKNESS + ENTITY_RADIUS * 1.1) * across
+    }
+}
@@ -0,0 +1,174 @@
+use rand::*;
+use rand_xorshift::XorShiftRng;
+
+use crate::core::vector::*;
+use crate::core::scalar::*;
+use crate::core::geo::polygon::*;
+
+// Width of every road, from kerb to kerb
+const ROAD_WIDTH: Scalar = 8.0;
+
+// Range of distances between neighbouring roads, not counting the roads themselves
+const BLOCK_SIZE_MIN: Scalar = 30.0;
+const BLOCK_SIZE_MAX: Scalar = 60.0;
+
+// Chance that a block is left open as a plaza instead of being built on
+const PLAZA_CHANCE: Scalar = 0.1;
+
+// Blocks are split into lots until the lots are no larger than this
+const LOT_AREA_MAX: Scalar = 450.0;
+const LOT_SIZE_MIN: Scalar = 12.0;
+
+// Space left between a building and the edges of its lot. Neighbouring buildings
+// end up twice this far apart, leaving an alley wide enough to walk down
+const LOT_SETBACK: Scalar = 2.5;
+
+const EMPTY_LOT_CHANCE: Scalar = 0.1;
+const L_SHAPE_CHANCE: Scalar = 0.3;
+const ROTATED_CHANCE: Scalar = 0.3;
+
+// Largest angle a rotated building is turned by, in radians
+const ROTATION_MAX: Scalar = 0.6;
+
+// A city laid out as a grid of roads, with the blocks between them either built on or
+// left open as plazas. Everything lies within the square from the origin to size
+pub struct City {
+    pub size: Scalar,
+    pub roads: Vec<Polygon>,
+    pub plazas: Vec<Polygon>,
+    pub buildings: Vec<Polygon>,
+}
+
+// Generate a city about side_length across. The same seed always gives the same city
+pub fn generate_city(side_length: Scalar, random_seed: u32) -> City {
+    let mut rng = XorShiftRng::seed_from_u64(random_seed as u64);
+
+    let road_xs = road_positions(&mut rng, side_length);
+    let road_ys = road_positions(&mut rng, side_length);
+    let size = road_xs.last().unwrap().max(*road_ys.last().unwrap());
+
+    let mut city = City {
+        size,
+        roads: vec!(),
+        plazas: vec!(),
+        buildings: vec!(),
+    };
+
+    // Roads run the full length of the city in both directions
+    for &x in &road_xs {
+        city.roads.push(rectangle(vector2(x - ROAD_WIDTH / 2.0, 0.0), vector2(x + ROAD_WIDTH / 2.0, size)));
+    }
+    for &y in &road_ys {
+        city.roads.push(rectangle(vector2(0.0, y - ROAD_WIDTH / 2.0), vector2(size, y + ROAD_WIDTH / 2.0)));
+    }
+
+    // Every city gets at least one plaza
+    let block_count = (road_xs.len() - 1) * (road_ys.len() - 1);
+    let guaranteed_plaza = rng.gen_range(0, block_count);
+
+    for i in 0..road_xs.len() - 1 {
+        for j in 0..road_ys.len() - 1 {
+            let block_min = vector2(road_xs[i] + ROAD_WIDTH / 2.0, road_ys[j] + ROAD_WIDTH / 2.0);
+            let block_max = vector2(road_xs[i + 1] - ROAD_WIDTH / 2.0, road_ys[j + 1] - ROAD_WIDTH / 2.0);
+
+            let block_index = i * (road_ys.len() - 1) + j;
+            if block_index == guaranteed_plaza || rng.gen_range(0.0, 1.0) < PLAZA_CHANCE {
+                city.plazas.push(rectangle(block_min, block_max));
+                continue;
+            }
+
+            for (lot_min, lot_max) in subdivide_block(&mut rng, block_min, block_max) {
+                if rng.gen_range(0.0, 1.0) < EMPTY_LOT_CHANCE {
+                    continue;
+                }
+                city.buildings.push(generate_building(&mut rng, lot_min, lot_max));
+            }
+        }
+    }
+
+    city
+}
+
+// Positions of the centre lines of the roads running across one axis of the city
+fn road_positions(rng: &mut XorShiftRng, side_length: Scalar) -> Vec<Scalar> {
+    let mut positions = vec![ROAD_WIDTH / 2.0];
+    while *positions.last().unwrap() < side_length {
+        let position = positions.last().unwrap() + ROAD_WIDTH + rng.gen_range(BLOCK_SIZE_MIN, BLOCK_SIZE_MAX);
+        positions.push(position);
+    }
+    positions
+}
+
+// Split a block into lots by repeatedly cutting the largest pieces across their long side
+fn subdivide_block(rng: &mut XorShiftRng, min: Vector2, max: Vector2) -> Vec<(Vector2, Vector2)> {
+    let mut lots = vec!();
+    let mut pieces = vec![(min, max)];
+
+    while let Some((min, max)) = pieces.pop() {
+        let size = max - min;
+        let long_side = size.x.max(size.y);
+        if size.x * size.y <= LOT_AREA_MAX || long_side < 2.0 * LOT_SIZE_MIN {
+            lots.push((min, max));
+            continue;
+        }
+
+        let cut = rng.gen_range(0.35, 0.65) * long_side;
+        let cut = cut.max(LOT_SIZE_MIN).min(long_side - LOT_SIZE_MIN);
+        if size.x >= size.y {
+            pieces.push((min, vector2(min.x + cut, max.y)));
+            pieces.push((vector2(min.x + cut, min.y), max));
+        } else {
+            pieces.push((min, vector2(max.x, min.y + cut)));
+            pieces.push((vector2(min.x, min.y + cut), max));
+        }
+    }
+
+    lots
+}
+
+// A building that fits inside the lot, leaving the setback clear around it
+fn generate_building(rng: &mut XorShiftRng, lot_min: Vector2, lot_max: Vector2) -> Polygon {
+    let center = 0.5 * (lot_min + lot_max);
+    let width = lot_max.x - lot_min.x - 2.0 * LOT_SETBACK;
+    let height = lot_max.y - lot_min.y - 2.0 * LOT_SETBACK;
+    let (w, h) = (width / 2.0, height / 2.0);
+
+    // Footprint centred on the origin
+    let mut footprint = if rng.gen_range(0.0, 1.0) < L_SHAPE_CHANCE {
+        // Cut a rectangle out of the top right corner, then mirror to pick which corner is missing
+        let cut_w = width * rng.gen_range(0.35, 0.6);
+        let cut_h = height * rng.gen_range(0.35, 0.6);
+        let flip_x = if rng.gen::<bool>() { -1.0 } else { 1.0 };
+        let flip_y = if rng.gen::<bool>() { -1.0 } else { 1.0 };
+        vec![
+            vector2(-w, -h),
+            vector2(w, -h),
+            vector2(w, h - cut_h),
+            vector2(w - cut_w, h - cut_h),
+            vector2(w - cut_w, h),
+            vector2(-w, h),
+        ].into_iter()
+            .map(|corner| vector2(corner.x * flip_x, corner.y * flip_y))
+            .collect()
+    } else {
+        vec![vector2(-w, -h), vector2(w, -h), vector2(w, h), vector2(-w, h)]
+    };
+
+    if rng.gen_range(0.0, 1.0) < ROTATED_CHANCE {
+        // Shrink the footprint so that its rotated bounding box still fits the lot
+        let angle = rng.gen_range(-ROTATION_MAX, ROTATION_MAX);
+        let (sin, cos) = angle.sin_cos();
+        let (abs_sin, abs_cos) = (sin.abs(), cos.abs());
+        let scale = (width / (width * abs_cos + height * abs_sin))
+            .min(height / (width * abs_sin + height * abs_cos));
+        footprint = footprint.into_iter()
+            .map(|corner| scale * vector2(cos * corner.x - sin * corner.y, sin * corner.x + cos * corner.y))
+            .collect();
+    }
+
+    Polygon(footprint.into_iter().map(|corner| corner + center).collect())
+}
+
+fn rectangle(min: Vector2, max: Vector2) -> Polygon {
+    Polygon(vec![min, vector2(max.x, min.y), max, vector2(min.x, max.y)])
+}
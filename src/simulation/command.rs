@@ -0,0 +1,345 @@
+use crate::core::vector::*;
+use crate::core::scalar::*;
+use crate::simulation::ai::pathfinding::find_path;
+
+use serde::{Serialize, Deserialize};
+
+use super::barricade::*;
+use super::formation::*;
+use super::reinforcements::MapEdge;
+use super::shelter::leave_shelter;
+use super::state::*;
+
+// A player action. All player input reaches the simulation through this type,
+// so a match can be reproduced from its seed and the log of commands issued
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Command {
+    // Select the given entities, either replacing the current selection or adding to it
+    Select {
+        entities: Vec<usize>,
+        add: bool,
+    },
+    // Take the given entities out of the selection
+    Deselect {
+        entities: Vec<usize>,
+    },
+    // Make the selected cops the only members of the control group
+    AssignGroup {
+        group: usize,
+    },
+    // Replace the selection with the members of the control group
+    RecallGroup {
+        group: usize,
+    },
+    // Order the selected cops to move to the waypoint, each taking their own place in the
+    // formation around it. Queued orders are carried out once the cops have finished their
+    // earlier orders, instead of straight away
+    Move {
+        waypoint: Vector2,
+        formation: Formation,
+        queue: bool,
+    },
+    // Order the selected cops to sprint to the waypoint
+    Sprint {
+        waypoint: Vector2,
+        formation: Formation,
+        queue: bool,
+    },
+    // Order the selected cops to attack the zombie
+    Attack {
+        target_index: usize,
+        queue: bool,
+    },
+    // Order the selected cops to walk a loop through the waypoints until told otherwise
+    Patrol {
+        waypoints: Vec<Vector2>,
+        queue: bool,
+    },
+    // Order the selected cops to hold the anchor point against any zombie in sight
+    Guard {
+        anchor: Vector2,
+        queue: bool,
+    },
+    // Order the selected cops to hold out inside the building
+    Garrison {
+        building_index: usize,
+    },
+    // Order the selected cops to build a barricade between the two points
+    Barricade {
+        start: Vector2,
+        end: Vector2,
+    },
+    // Call in a squad of cops from the edge of the map, to gather at the rally point
+    CallReinforcements {
+        edge: MapEdge,
+        rally_point: Vector2,
+    },
+    // Debugging cheats that end the match on the spot, by killing everyone but the zombies
+    // or only the zombies
+    DebugZombiesWin,
+    DebugHumansWin,
+}
+
+// A command stamped with the tick at which it was applied
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct TimedCommand {
+    pub tick: u64,
+    pub command: Command,
+}
+
+pub fn apply_command(state: &mut State, command: &Command) {
+    match command {
+        Command::Select { entities, add } => {
+            if !add {
+                state.selection.clear();
+            }
+            for i in entities {
+                state.selection.insert(*i);
+            }
+        }
+        Command::Deselect { entities } => {
+            for i in entities {
+                state.selection.remove(i);
+            }
+        }
+        Command::AssignGroup { group } =>
+            assign_control_group(state, *group),
+        Command::RecallGroup { group } => {
+            state.selection = state.control_group(*group).iter().cloned().collect();
+        }
+        Command::Move { waypoint, formation, queue } =>
+            issue_move_order(state, *waypoint, MoveMode::Moving, *formation, *queue),
+        Command::Sprint { waypoint, formation, queue } =>
+            issue_move_order(state, *waypoint, MoveMode::Sprinting, *formation, *queue),
+        Command::Attack { target_index, queue } =>
+            issue_attack_order(state, *target_index, *queue),
+        Command::Patrol { waypoints, queue } if waypoints.len() >= 2 =>
+            issue_order(state, CopState::Patrolling { waypoints: waypoints.clone(), next: 0, path: None }, *queue),
+        // A patrol needs at least two points to go between
+        Command::Patrol { .. } => (),
+        Command::Guard { anchor, queue } =>
+            issue_order(state, CopState::Guarding { anchor: *anchor, path: None }, *queue),
+        Command::Garrison { building_index } =>
+            issue_garrison_order(state, *building_index),
+        Command::Barricade { start, end } =>
+            issue_barricade_order(state, *start, *end),
+        Command::CallReinforcements { edge, rally_point } =>
+            state.reinforcements.call(*edge, *rally_point),
+        Command::DebugZombiesWin => {
+            for entity in &mut state.entities {
+                match entity.behaviour {
+                    Behaviour::Zombie { .. } => (),
+                    _ => entity.behaviour = Behaviour::Dead,
+                }
+            }
+        }
+        Command::DebugHumansWin => {
+            for entity in &mut state.entities {
+                match entity.behaviour {
+                    Behaviour::Human { .. } | Behaviour::Cop { .. } => (),
+                    _ => entity.behaviour = Behaviour::Dead,
+                }
+            }
+        }
+    }
+}
+
+fn assign_control_group(state: &mut State, group: usize) {
+    if group >= state.control_groups.len() {
+        return;
+    }
+
+    let mut members: Vec<usize> = state.selection.iter()
+        .cloned()
+        .filter(|&i| match state.entities[i].behaviour { Behaviour::Cop { .. } => true, _ => false })
+        .collect();
+    members.sort();
+    state.control_groups[group] = members;
+}
+
+// Add the order to the end of a busy cop's queue, where the path is planned once the
+// cop gets to it. Returns false if the order should replace what they are doing instead
+fn queue_order(behaviour: &mut Behaviour, order: CopState, queue: bool) -> bool {
+    match behaviour {
+        Behaviour::Cop { state_stack, queued_orders, .. } if queue && !state_stack.is_empty() => {
+            queued_orders.push(order);
+            true
+        }
+        _ => false
+    }
+}
+
+// Give the selected cops an order that plans its own path once they start on it
+fn issue_order(state: &mut State, order: CopState, queue: bool) {
+    for i in &state.selection {
+        if queue_order(&mut state.entities[*i].behaviour, order.clone(), queue) {
+            continue;
+        }
+
+        // Cops in a building have to come out to follow the order
+        leave_shelter(&mut state.shelters, &mut state.entities, *i);
+
+        if let Behaviour::Cop { state_stack, queued_orders, .. } = &mut state.entities[*i].behaviour {
+            // Make the cop stop what they are doing
+            state_stack.clear();
+            queued_orders.clear();
+
+            state_stack.push(order.clone());
+        }
+    }
+}
+
+// Where each of the cops at the positions should go to form up around the point, facing
+// the way they are headed
+fn formation_waypoints(state: &State, point: Vector2, formation: Formation, positions: &[Vector2]) -> Vec<Vector2> {
+    if positions.is_empty() {
+        return vec!();
+    }
+
+    let centroid = positions.iter().fold(Vector2::zero(), |sum, position| sum + *position) / positions.len() as Scalar;
+    let facing = if (point - centroid).length_squared() > 0.0 {
+        (point - centroid).normalize()
+    } else {
+        vector2(0.0, 1.0)
+    };
+
+    // A cop whose place falls inside a building makes do with the point itself
+    let slots: Vec<Vector2> = formation.slots(point, facing, positions.len())
+        .into_iter()
+        .map(|slot| if state.building_outlines.iter().any(|outline| outline.contains_point(slot)) { point } else { slot })
+        .collect();
+
+    assign_slots(positions, &slots)
+}
+
+fn issue_move_order(state: &mut State, point: Vector2, mode: MoveMode, formation: Formation, queue: bool) {
+    // In order of index, so that the cops take the same places when the match is replayed
+    let mut cops: Vec<usize> = state.selection.iter()
+        .cloned()
+        .filter(|&i| match state.entities[i].behaviour { Behaviour::Cop { .. } => true, _ => false })
+        .collect();
+    cops.sort();
+
+    let positions: Vec<Vector2> = cops.iter().map(|&i| state.entities[i].position).collect();
+    let waypoints = formation_waypoints(state, point, formation, &positions);
+
+    for (i, waypoint) in cops.into_iter().zip(waypoints) {
+        let order = CopState::Moving { waypoint, mode, path: None };
+        if queue_order(&mut state.entities[i].behaviour, order, queue) {
+            continue;
+        }
+
+        // Cops in a building have to come out to follow the order
+        leave_shelter(&mut state.shelters, &mut state.entities, i);
+        let Entity { position, behaviour, .. } = &mut state.entities[i];
+
+        match behaviour {
+            Behaviour::Cop { state_stack, queued_orders, .. } => {
+                // Make the cop stop what they are doing
+                state_stack.clear();
+                queued_orders.clear();
+
+                let path = find_path(
+                    *position,
+                    waypoint,
+                    &state.visibility_graph);
+
+                state_stack.push(CopState::Moving { waypoint, mode, path });
+            }
+            _ => ()
+        }
+    }
+}
+
+fn issue_attack_order(state: &mut State, target_index: usize, queue: bool) {
+    let target_position = state.entities[target_index].position;
+
+    for i in &state.selection {
+        let order = CopState::AttackingZombie { target_index, path: None };
+        if queue_order(&mut state.entities[*i].behaviour, order, queue) {
+            continue;
+        }
+
+        // Cops in a building have to come out to follow the order
+        leave_shelter(&mut state.shelters, &mut state.entities, *i);
+        let Entity { position, behaviour, .. } = &mut state.entities[*i];
+
+        match behaviour {
+            Behaviour::Cop { state_stack, queued_orders, .. } => {
+                // Make the cop stop what they are doing
+                state_stack.clear();
+                queued_orders.clear();
+
+                let path = find_path(
+                    *position,
+                    target_position,
+                    &state.visibility_graph);
+
+                state_stack.push(CopState::AttackingZombie { target_index, path });
+            }
+            _ => ()
+        }
+    }
+}
+
+fn issue_garrison_order(state: &mut State, building_index: usize) {
+    let door = state.shelters[building_index].door;
+
+    for i in &state.selection {
+        // Cops already in another building come out and head over
+        if state.entities[*i].sheltering_in != Some(building_index) {
+            leave_shelter(&mut state.shelters, &mut state.entities, *i);
+        }
+        let Entity { position, behaviour, .. } = &mut state.entities[*i];
+
+        match behaviour {
+            Behaviour::Cop { state_stack, queued_orders, .. } => {
+                // Make the cop stop what they are doing
+                state_stack.clear();
+                queued_orders.clear();
+
+                let path = find_path(
+                    *position,
+                    door,
+                    &state.visibility_graph);
+
+                state_stack.push(CopState::Garrisoning { building_index, path });
+            }
+            _ => ()
+        }
+    }
+}
+
+fn issue_barricade_order(state: &mut State, start: Vector2, end: Vector2) {
+    // Someone has to build it
+    let builders: Vec<usize> = state.selection.iter()
+        .cloned()
+        .filter(|&i| match state.entities[i].behaviour { Behaviour::Cop { .. } => true, _ => false })
+        .collect();
+    if builders.is_empty() || (end - start).length() < BARRICADE_MIN_LENGTH {
+        return;
+    }
+
+    let barricade = Barricade::new(start, end);
+    let barricade_index = state.barricades.len();
+
+    for i in builders {
+        leave_shelter(&mut state.shelters, &mut state.entities, i);
+        let Entity { position, behaviour, .. } = &mut state.entities[i];
+
+        if let Behaviour::Cop { state_stack, queued_orders, .. } = behaviour {
+            // Make the cop stop what they are doing
+            state_stack.clear();
+            queued_orders.clear();
+
+            let path = find_path(
+                *position,
+                barricade.work_position(*position),
+                &state.visibility_graph);
+
+            state_stack.push(CopState::Building { barricade_index, path });
+        }
+    }
+
+    state.barricades.push(barricade);
+}
@@ -4,8 +4,9 @@ use crate::core::matrix::*;
 use crate::core::geo::intersect::rectangle_point::*;
 use crate::core::geo::segment2::*;
 use crate::simulation::game_state::GameState;
-use crate::simulation::state::MoveMode;
-use crate::simulation::ai::pathfinding::*;
+use crate::simulation::command::Command;
+use crate::simulation::formation::Formation;
+use crate::simulation::reinforcements::{MapEdge, nearest_map_edge};
 
 use glium_sdl2::SDL2Facade;
 use sdl2::event::Event;
@@ -15,15 +16,39 @@ use sdl2::keyboard::Keycode;
 
 use super::state::*;
 
+// Longest gap between two clicks or key presses for them to count as a double click
+const DOUBLE_TAP_MILLISECONDS: u32 = 300;
+
 #[derive(Clone)]
 pub struct Control {
     pub mouse_drag: bool,
     pub shift_pressed: bool,
+    pub ctrl_pressed: bool,
     pub drag_start_mouse_coord: Vector2,
     pub drag_vertex_start: Vector2,
     pub drag_vertex_end: Vector2,
     pub last_click_time: Instant,
-    pub last_right_click_time: Instant
+    pub last_right_click_time: Instant,
+    // Set after pressing B, so that the next two right clicks mark out a barricade
+    pub placing_barricade: bool,
+    pub barricade_start: Option<Vector2>,
+    // Set after pressing R or the reinforcements button, so that the next right click
+    // picks the edge of the map the squad comes in from, whichever is closest to the
+    // click, and the one after that the rally point
+    pub calling_reinforcements: bool,
+    pub reinforcement_edge: Option<MapEdge>,
+    // Set after pressing P, so that right clicks mark out the waypoints of a patrol
+    // until P is pressed again
+    pub placing_patrol: bool,
+    pub patrol_waypoints: Vec<Vector2>,
+    // Set after pressing G, so that the next right click picks the point to guard
+    pub placing_guard: bool,
+    // How the selected cops arrange themselves when sent somewhere together
+    pub formation: Formation,
+    // Control group last recalled and when, to tell a double tap
+    pub last_group_recall: Option<(usize, Instant)>,
+    // Point in the world the camera should center on, set by double tapping a group
+    pub camera_focus: Option<Vector2>,
 }
 
 impl Control {
@@ -32,45 +57,62 @@ impl Control {
         Control {
             mouse_drag: false,
             shift_pressed: false,
+            ctrl_pressed: false,
             drag_start_mouse_coord: Vector2::zero(),
             drag_vertex_start: Vector2::zero(),
             drag_vertex_end: Vector2::zero(),
             last_click_time: Instant::now(),
-            last_right_click_time: Instant::now()
+            last_right_click_time: Instant::now(),
+            placing_barricade: false,
+            barricade_start: None,
+            calling_reinforcements: false,
+            reinforcement_edge: None,
+            placing_patrol: false,
+            patrol_waypoints: vec!(),
+            placing_guard: false,
+            formation: Formation::default(),
+            last_group_recall: None,
+            camera_focus: None,
         }
     }
 
-    pub fn click_select(&mut self, state: &mut State, window: &SDL2Facade, camera_frame: Mat4, mouse_pos: Vector2) {
-        if !self.shift_pressed {
-            state.selection.clear();
-        }
+    pub fn click_select(&mut self, state: &State, window: &SDL2Facade, camera_frame: Mat4, mouse_pos: Vector2, commands: &mut Vec<Command>) {
+        let mut selection = vec!();
         let m_pos = &mut Vector2{ x : mouse_pos.x, y : mouse_pos.y};
         translate_mouse_to_camera(m_pos, window.window().size());
         translate_camera_to_world(m_pos, camera_frame);
 
         for i in 0..state.entities.len() {
-            let entity = &mut state.entities[i];
+            let entity = &state.entities[i];
             match entity.behaviour {
                 Behaviour::Cop {..} => {
                     let entity_pos = entity.position;
                     if is_click_on_entity(entity_pos, *m_pos) {
-                        state.selection.insert(i);
+                        selection.push(i);
                         break;
                     }
                 }
                 _ => ()
             }
         }
+
+        // Shift clicking a selected cop takes them back out of the selection
+        if self.shift_pressed && selection.len() == 1 && state.selection.contains(&selection[0]) {
+            commands.push(Command::Deselect { entities: selection });
+            return;
+        }
+
+        commands.push(Command::Select { entities: selection, add: self.shift_pressed });
     }
 
-    pub fn double_click_select(&mut self, state: &mut State, camera_frame: Mat4, mouse_pos: Vector2, window: &SDL2Facade) {
-        state.selection.clear();
+    pub fn double_click_select(&mut self, state: &State, camera_frame: Mat4, mouse_pos: Vector2, window: &SDL2Facade, commands: &mut Vec<Command>) {
+        let mut selection = vec!();
         let m_pos = &mut Vector2{ x : mouse_pos.x, y : mouse_pos.y};
         translate_mouse_to_camera(m_pos, window.window().size());
         translate_camera_to_world(m_pos, camera_frame);
 
         for i in 0..state.entities.len() {
-            let entity = &mut state.entities[i];
+            let entity = &state.entities[i];
             match entity.behaviour {
                 Behaviour::Cop {..} => {
                     let x_pos: Scalar = entity.position.x;
@@ -79,14 +121,14 @@ impl Control {
                         && m_pos.y <= y_pos + 0.5 && m_pos.y >= y_pos - 0.5 {
 
                         for j in 0..state.entities.len() {
-                            let entity1 = &mut state.entities[j];
+                            let entity1 = &state.entities[j];
                             match entity1.behaviour {
                                 Behaviour::Cop {..} => {
                                     let entity_pos = &mut Vector2{ x: entity1.position.x, y: entity1.position.y };
                                     translate_world_to_camera(entity_pos, camera_frame);
                                     if entity_pos.x <= 1.0 && entity_pos.x >= -1.0
                                         && entity_pos.y <= 1.0 && entity_pos.y >= -1.0 {
-                                        state.selection.insert(j);
+                                        selection.push(j);
                                     }
                                 }
                                 _ => ()
@@ -99,12 +141,12 @@ impl Control {
                 _ => ()
             }
         }
+
+        commands.push(Command::Select { entities: selection, add: false });
     }
 
-    pub fn drag_select(&mut self, state: &mut State, window: &SDL2Facade, camera_frame: Mat4, mouse_end: Vector2) {
-        if !self.shift_pressed {
-            state.selection.clear();
-        }
+    pub fn drag_select(&mut self, state: &State, window: &SDL2Facade, camera_frame: Mat4, mouse_end: Vector2, commands: &mut Vec<Command>) {
+        let mut selection = vec!();
         let m_start_pos = &mut Vector2{ x : self.drag_start_mouse_coord.x, y : self.drag_start_mouse_coord.y};
         let m_end_pos = &mut Vector2{ x : mouse_end.x, y : mouse_end.y};
         translate_mouse_to_camera(m_start_pos, window.window().size());
@@ -119,24 +161,43 @@ impl Control {
         translate_camera_to_world(m_end_pos, camera_frame);
 
         for i in 0..state.entities.len() {
-            let entity = &mut state.entities[i];
+            let entity = &state.entities[i];
             match entity.behaviour {
                 Behaviour::Cop {..} => {
                     let entity_pos = entity.position;
                     if check_bounding_box(*m_start_pos, *m_end_pos, entity_pos) {
-                        state.selection.insert(i);
+                        selection.push(i);
                     }
                 }
                 _ => ()
             }
         }
+
+        commands.push(Command::Select { entities: selection, add: self.shift_pressed });
     }
 
     // Issue an order to selected police
-    pub fn issue_police_order(&mut self, order: PoliceOrder, simulation: &mut State, window: &SDL2Facade, camera_frame: Mat4, mouse_pos: Vector2) {
+    pub fn issue_police_order(&mut self, order: PoliceOrder, simulation: &State, window: &SDL2Facade, camera_frame: Mat4, mouse_pos: Vector2, commands: &mut Vec<Command>) {
         let mut m_pos = Vector2{ x: mouse_pos.x, y: mouse_pos.y };
         translate_mouse_to_camera(&mut m_pos, window.window().size());
         translate_camera_to_world(&mut m_pos, camera_frame);
+        self.issue_police_order_at(order, simulation, m_pos, commands);
+    }
+
+    // Issue an order to selected police at the point in the world
+    pub fn issue_police_order_at(&mut self, order: PoliceOrder, simulation: &State, point: Vector2, commands: &mut Vec<Command>) {
+        let mut m_pos = point;
+
+        // Patrols and guards only care about where to go
+        let moving = order == PoliceOrder::Move || order == PoliceOrder::Sprint;
+
+        // Clicking a building with its door intact garrisons it
+        for (i, building) in simulation.buildings.iter().enumerate() {
+            if moving && building.contains_point(m_pos) && !simulation.shelters[i].is_breached() {
+                commands.push(Command::Garrison { building_index: i });
+                return;
+            }
+        }
 
         // Check if m_pos is inside a building
         for building in &simulation.buildings {
@@ -171,7 +232,7 @@ impl Control {
 
         // Check if any zombie is within the click
         for i in 0..simulation.entities.len() {
-            let entity = &mut simulation.entities[i];
+            let entity = &simulation.entities[i];
             match entity.behaviour {
                 Behaviour::Zombie {..} => {
                     let entity_pos = entity.position;
@@ -183,44 +244,89 @@ impl Control {
             }
         }
 
-        for i in &simulation.selection {
+        // If no zombie clicked, issue regular move order, else issue special attack order
+        // Holding shift queues the order up after the ones already given
+        let queue = self.shift_pressed;
+        commands.push(match (zombie_index, order) {
+            (Some(index), PoliceOrder::Move) | (Some(index), PoliceOrder::Sprint) =>
+                Command::Attack { target_index: index, queue },
+            (_, PoliceOrder::Move) => Command::Move { waypoint: m_pos, formation: self.formation, queue },
+            (_, PoliceOrder::Sprint) => Command::Sprint { waypoint: m_pos, formation: self.formation, queue },
+            (_, PoliceOrder::Guard) => {
+                self.placing_guard = false;
+                Command::Guard { anchor: m_pos, queue }
+            }
+            // The order is only given once every waypoint has been placed
+            (_, PoliceOrder::Patrol) => {
+                self.patrol_waypoints.push(m_pos);
+                return;
+            }
+        });
+    }
 
-            let Entity {position, behaviour, ..} = &mut simulation.entities[*i];
+    // Mark one end of the barricade the selected police should build. The second
+    // end issues the order
+    pub fn place_barricade_end(&mut self, window: &SDL2Facade, camera_frame: Mat4, mouse_pos: Vector2, commands: &mut Vec<Command>) {
+        let mut m_pos = Vector2{ x: mouse_pos.x, y: mouse_pos.y };
+        translate_mouse_to_camera(&mut m_pos, window.window().size());
+        translate_camera_to_world(&mut m_pos, camera_frame);
 
-            match behaviour {
-                Behaviour::Cop { ref mut state_stack, .. } => {
+        match self.barricade_start {
+            None => self.barricade_start = Some(m_pos),
+            Some(start) => {
+                commands.push(Command::Barricade { start, end: m_pos });
+                self.placing_barricade = false;
+                self.barricade_start = None;
+            }
+        }
+    }
 
-                    // Make the cop stop what they are doing
-                    state_stack.clear();
+    pub fn call_reinforcements(&mut self, state: &State, window: &SDL2Facade, camera_frame: Mat4, mouse_pos: Vector2, commands: &mut Vec<Command>) {
+        let mut m_pos = Vector2{ x: mouse_pos.x, y: mouse_pos.y };
+        translate_mouse_to_camera(&mut m_pos, window.window().size());
+        translate_camera_to_world(&mut m_pos, camera_frame);
 
-                    let path = find_path(
-                        *position,
-                        m_pos,
-                        &simulation.buildings,
-                        &simulation.building_outlines);
+        match self.reinforcement_edge {
+            None => self.reinforcement_edge = Some(nearest_map_edge(state, m_pos)),
+            Some(edge) => {
+                commands.push(Command::CallReinforcements { edge, rally_point: m_pos });
+                self.calling_reinforcements = false;
+                self.reinforcement_edge = None;
+            }
+        }
+    }
 
-                    // If no zombie clicked, issue regular move order, else issue special attack order
+    // Ctrl and a number assigns the selection to that group, and the number alone
+    // recalls it. Recalling the same group twice in quick succession also centers the
+    // camera on it
+    pub fn handle_control_group_key(&mut self, group: usize, state: &State, commands: &mut Vec<Command>) {
+        if self.ctrl_pressed {
+            commands.push(Command::AssignGroup { group });
+            self.last_group_recall = None;
+            return;
+        }
 
+        commands.push(Command::RecallGroup { group });
 
-                    state_stack.push(match zombie_index {
-                        None =>
-                            CopState::Moving {
-                                waypoint: m_pos,
-                                mode: match order {
-                                    PoliceOrder::Move => MoveMode::Moving,
-                                    PoliceOrder::Sprint => MoveMode::Sprinting,
-                                },
-                                path: path
-                            },
-                        Some(index) =>
-                            CopState::AttackingZombie {
-                                target_index: index,
-                                path: path
-                            }
-                    })
-                }
-                _ => ()
+        let current_time = Instant::now();
+        let double_tap = match self.last_group_recall {
+            Some((last_group, last_time)) => {
+                let duration = current_time.duration_since(last_time);
+                last_group == group && duration.as_secs() == 0 && duration.subsec_millis() < DOUBLE_TAP_MILLISECONDS
+            }
+            None => false,
+        };
+
+        let members = state.control_group(group);
+        if double_tap && !members.is_empty() {
+            let mut center = Vector2::zero();
+            for i in members {
+                center += state.entities[*i].position;
             }
+            self.camera_focus = Some(center / members.len() as Scalar);
+            self.last_group_recall = None;
+        } else {
+            self.last_group_recall = Some((group, current_time));
         }
     }
 
@@ -242,41 +348,54 @@ impl Control {
         self.drag_vertex_end.y = drag_end_proj.y;
     }
 
-    pub fn handle_event(&mut self, event: Event, window: &SDL2Facade, camera_frame: Mat4, state: &mut State, game_state: &mut GameState) {
+    pub fn handle_event(&mut self, event: Event, window: &SDL2Facade, camera_frame: Mat4, state: &State, game_state: &mut GameState, commands: &mut Vec<Command>) {
         match event {
             Event::KeyDown { keycode: Some(key), ..} => {
                 match key {
                     Keycode::Space => {
                         game_state.game_paused = !game_state.game_paused;
                     }
-                    // Debugging purposes
-                    Keycode::F1 => {
-                        for i in 0..state.entities.len() {
-                            let mut entity = &mut state.entities[i];
-                            match entity.behaviour {
-                                // do nothing for zombie
-                                Behaviour::Zombie { .. } => (),
-                                // kill everything else
-                                _ => entity.behaviour = Behaviour::Dead
-                            }
-                        }
-                        game_state.zombies_win = true;
-                    },
-                    Keycode::F2 => {
-                        for i in 0..state.entities.len() {
-                            let mut entity = &mut state.entities[i];
-                            match entity.behaviour {
-                                Behaviour::Human { .. } => (),
-                                Behaviour::Cop { .. } => (),
-                                _ => entity.behaviour = Behaviour::Dead
-                            }
-                        }
-                        game_state.humans_win = true;
-                    },
                     Keycode::LShift => {
                         self.shift_pressed = true;
                     }
-                    _ => ()
+                    Keycode::LCtrl | Keycode::RCtrl => {
+                        self.ctrl_pressed = true;
+                    }
+                    // Start marking out a barricade, or cancel
+                    Keycode::B => {
+                        self.placing_barricade = !self.placing_barricade;
+                        self.barricade_start = None;
+                    }
+                    // Start marking out a patrol, or finish it. It needs at least two
+                    // waypoints, and is cancelled without them
+                    Keycode::P => {
+                        if self.placing_patrol && self.patrol_waypoints.len() >= 2 {
+                            commands.push(Command::Patrol {
+                                waypoints: self.patrol_waypoints.clone(),
+                                queue: self.shift_pressed
+                            });
+                        }
+                        self.placing_patrol = !self.placing_patrol;
+                        self.patrol_waypoints.clear();
+                    }
+                    // Pick a point to guard, or cancel
+                    Keycode::G => {
+                        self.placing_guard = !self.placing_guard;
+                    }
+                    // Switch to the next formation
+                    Keycode::F => {
+                        self.formation = self.formation.next();
+                    }
+                    // Pick an edge and rally point for reinforcements, or cancel
+                    Keycode::R if state.reinforcements.ready() => {
+                        self.calling_reinforcements = !self.calling_reinforcements;
+                        self.reinforcement_edge = None;
+                    }
+                    _ => {
+                        if let Some(group) = control_group_for_key(key) {
+                            self.handle_control_group_key(group, state, commands);
+                        }
+                    }
                 }
             }
             Event::KeyUp { keycode: Some(key), ..} => {
@@ -284,6 +403,9 @@ impl Control {
                     Keycode::LShift => {
                         self.shift_pressed = false;
                     }
+                    Keycode::LCtrl | Keycode::RCtrl => {
+                        self.ctrl_pressed = false;
+                    }
                     _ => ()
                 }
             }
@@ -315,7 +437,7 @@ impl Control {
             Event::MouseButtonUp { timestamp: _, window_id: _, which: _, mouse_btn, x, y } => {
                 self.mouse_drag = false;
                 let mouse_pos = Vector2 { x: x as f64, y: y as f64 };
-                let delta_millisecond = 300;
+                let delta_millisecond = DOUBLE_TAP_MILLISECONDS;
 
                 match mouse_btn {
                     MouseButton::Left { .. } => {
@@ -326,24 +448,36 @@ impl Control {
                             let current_time = Instant::now();
                             let duration = current_time.duration_since(self.last_click_time);
                             if duration.as_secs() == 0 && duration.subsec_millis() < delta_millisecond {
-                                self.double_click_select(state, camera_frame, mouse_pos, &window);
+                                self.double_click_select(state, camera_frame, mouse_pos, &window, commands);
                             } else {
-                                self.click_select(state, &window, camera_frame, mouse_pos);
+                                self.click_select(state, &window, camera_frame, mouse_pos, commands);
                             }
                             self.last_click_time = current_time;
                         } else {
-                            self.drag_select(state, &window, camera_frame, mouse_pos);
+                            self.drag_select(state, &window, camera_frame, mouse_pos, commands);
                         }
                     }
+                    MouseButton::Right { .. } if self.calling_reinforcements => {
+                        self.call_reinforcements(state, &window, camera_frame, mouse_pos, commands);
+                    }
+                    MouseButton::Right { .. } if self.placing_patrol => {
+                        self.issue_police_order(PoliceOrder::Patrol, state, &window, camera_frame, mouse_pos, commands);
+                    }
+                    MouseButton::Right { .. } if self.placing_guard => {
+                        self.issue_police_order(PoliceOrder::Guard, state, &window, camera_frame, mouse_pos, commands);
+                    }
+                    MouseButton::Right { .. } if self.placing_barricade => {
+                        self.place_barricade_end(&window, camera_frame, mouse_pos, commands);
+                    }
                     MouseButton::Right { .. } => {
                         let current_time = Instant::now();
                         let duration = current_time.duration_since(self.last_right_click_time);
                         if duration.as_secs() == 0 && duration.subsec_millis() < delta_millisecond {
                             // double right click to sprint
-                            self.issue_police_order(PoliceOrder::Sprint, state, &window, camera_frame, mouse_pos);
+                            self.issue_police_order(PoliceOrder::Sprint, state, &window, camera_frame, mouse_pos, commands);
                         } else {
                             // single right click for attack or attack move
-                            self.issue_police_order(PoliceOrder::Move, state, &window, camera_frame, mouse_pos);
+                            self.issue_police_order(PoliceOrder::Move, state, &window, camera_frame, mouse_pos, commands);
                         }
                         self.last_right_click_time = current_time;
                     }
@@ -375,6 +509,16 @@ pub fn translate_world_to_camera(vec: &mut Vector2, matrix: Mat4) {
     vec.y = new_vec2.y;
 }
 
+// Index of the control group bound to the key, for the number keys 1 to 9
+fn control_group_for_key(key: Keycode) -> Option<usize> {
+    let keys = [
+        Keycode::Num1, Keycode::Num2, Keycode::Num3,
+        Keycode::Num4, Keycode::Num5, Keycode::Num6,
+        Keycode::Num7, Keycode::Num8, Keycode::Num9,
+    ];
+    keys.iter().position(|&k| k == key)
+}
+
 fn is_click_on_entity(entity_pos: Vector2, m_pos: Vector2) -> bool {
     let entity_delta = 0.5;
     let x_pos = entity_pos.x;
@@ -383,7 +527,10 @@ fn is_click_on_entity(entity_pos: Vector2, m_pos: Vector2) -> bool {
         && m_pos.y <= y_pos + entity_delta && m_pos.y >= y_pos - entity_delta;
 }
 
+#[derive(Copy, Clone, PartialEq)]
 pub enum PoliceOrder {
     Move,
     Sprint,
+    Patrol,
+    Guard,
 }
@@ -0,0 +1,30 @@
+use crate::core::vector::*;
+use crate::core::scalar::*;
+
+// Entities on less than this much health leave a trail of blood behind them as they move
+pub const DRAG_TRAIL_HEALTH: Scalar = 40.0;
+pub const DRAG_TRAIL_MIN_SPEED: Scalar = 0.2;
+
+// Ticks between the marks of a trail
+pub const DRAG_TRAIL_INTERVAL: u64 = 30;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum DecalKind {
+    // Where someone was shot, bitten or turned
+    BloodSplat,
+    // Smeared along the way a wounded entity went
+    DragTrail,
+    // Where a bullet struck a wall
+    Scorch,
+}
+
+// A mark left on the ground. The simulation only reports where they are left, and
+// never looks at them again
+#[derive(Copy, Clone, Debug)]
+pub struct Decal {
+    pub kind: DecalKind,
+    pub position: Vector2,
+    // Direction the mark is stretched along
+    pub angle: Scalar,
+    pub radius: Scalar,
+}
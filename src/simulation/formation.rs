@@ -0,0 +1,99 @@
+use std::f64::consts::PI;
+
+use serde::{Serialize, Deserialize};
+
+use crate::core::vector::*;
+use crate::core::scalar::*;
+
+// Distance between neighbouring cops in a formation
+pub const FORMATION_SPACING: Scalar = 1.5;
+
+// How a group of cops given a move order arrange themselves around the waypoint
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Formation {
+    // Side by side, across the direction of travel
+    Line,
+    // An arrowhead with its point at the waypoint
+    Wedge,
+    // One behind the other, along the direction of travel
+    Column,
+    // Evenly spaced around the waypoint
+    Circle,
+}
+
+impl Default for Formation {
+    fn default() -> Formation {
+        Formation::Line
+    }
+}
+
+impl Formation {
+    // The formation the hotkey switches to from this one
+    pub fn next(&self) -> Formation {
+        match self {
+            Formation::Line => Formation::Wedge,
+            Formation::Wedge => Formation::Column,
+            Formation::Column => Formation::Circle,
+            Formation::Circle => Formation::Line,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Formation::Line => "Line",
+            Formation::Wedge => "Wedge",
+            Formation::Column => "Column",
+            Formation::Circle => "Circle",
+        }
+    }
+
+    // Where each of the cops should stand, with the formation centred on the point and
+    // facing along the direction, which should be a unit vector
+    pub fn slots(&self, center: Vector2, facing: Vector2, count: usize) -> Vec<Vector2> {
+        let right = facing.right();
+        let middle = 0.5 * (count as Scalar - 1.0);
+
+        (0..count).map(|i| {
+            let i_scalar = i as Scalar;
+            match self {
+                Formation::Line => center + (i_scalar - middle) * FORMATION_SPACING * right,
+                Formation::Column => center + (middle - i_scalar) * FORMATION_SPACING * facing,
+                // The first cop takes the point, the rest fall in behind on alternate sides
+                Formation::Wedge => {
+                    let rank = ((i + 1) / 2) as Scalar;
+                    let side = if i % 2 == 1 { -1.0 } else { 1.0 };
+                    center + rank * FORMATION_SPACING * (side * right - facing)
+                }
+                // Wide enough to leave the spacing between neighbours
+                Formation::Circle if count > 1 => {
+                    let radius = (FORMATION_SPACING * count as Scalar / (2.0 * PI)).max(FORMATION_SPACING);
+                    let angle = facing.angle() + 2.0 * PI * i_scalar / count as Scalar;
+                    center + radius * Vector2::from_angle(angle)
+                }
+                Formation::Circle => center,
+            }
+        }).collect()
+    }
+}
+
+// Match each cop to a slot, keeping the distance they have to go short. Returns the slot
+// for each position, in the same order
+pub fn assign_slots(positions: &[Vector2], slots: &[Vector2]) -> Vec<Vector2> {
+    let mut assigned = vec![Vector2::zero(); positions.len()];
+    let mut unassigned: Vec<usize> = (0..positions.len()).collect();
+
+    // Fill the slots in order, each with the closest cop still free
+    for slot in slots.iter().take(positions.len()) {
+        let (k, _) = unassigned.iter()
+            .enumerate()
+            .min_by(|(_, &a), (_, &b)| {
+                let distance_a = (positions[a] - *slot).length_squared();
+                let distance_b = (positions[b] - *slot).length_squared();
+                distance_a.partial_cmp(&distance_b).unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .unwrap();
+        assigned[unassigned.remove(k)] = *slot;
+    }
+
+    assigned
+}
@@ -1,10 +1,19 @@
-#[derive(Clone)]
+use serde::{Serialize, Deserialize};
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct GameState {
     pub tick: usize,
     pub game_paused: bool,
     pub terminate: bool,
     pub transition_game: bool,
     pub transition_menu: bool,
+    pub transition_replay: bool,
+    // Main menu asks for the next scenario in the list
+    pub cycle_scenario: bool,
+    pub save_requested: bool,
+    pub load_requested: bool,
+    // Debug toggle that shows the whole map, fog or not
+    pub fog_of_war_disabled: bool,
     // debug purposes for scene transition
     pub humans_win: bool,
     pub zombies_win: bool
@@ -17,6 +26,11 @@ impl GameState {
             game_paused: false,
             transition_game: false,
             transition_menu: false,
+            transition_replay: false,
+            cycle_scenario: false,
+            save_requested: false,
+            load_requested: false,
+            fog_of_war_disabled: false,
             terminate: false,
             humans_win: false,
             zombies_win: false,
@@ -3,95 +3,118 @@ use rand_xorshift::XorShiftRng;
 use std::collections::HashSet;
 use crate::core::vector::*;
 use crate::core::scalar::*;
-use crate::core::geo::polygon::*;
+use crate::core::geo::polygon::Polygon;
+use super::scenario::*;
+use super::reinforcements::Reinforcements;
+use super::statistics::Statistics;
+use super::shelter::Shelter;
 use super::state::*;
 
-const PORTION_OF_ENTITIES_COP: Scalar = 0.05;
-const PORTION_OF_ENTITIES_ZOMBIE: Scalar = 0.2;
+// How many random positions to try before giving up on finding one in walkable space
+const MAX_SPAWN_ATTEMPTS: u32 = 100;
 
-pub fn initial_state(entity_count: u32, random_seed: u32) -> State {
-    let entity_count_fp = entity_count as Scalar;
-    let cop_count: u32 = ((entity_count_fp * PORTION_OF_ENTITIES_COP) as u32).max(1);
-    let zombie_count: u32 = ((entity_count_fp * PORTION_OF_ENTITIES_ZOMBIE) as u32).max(1);
-    let human_count: u32 = entity_count - (cop_count + zombie_count);
+pub fn initial_state(scenario: &Scenario, random_seed: u32) -> State {
+    let cop_count = scenario.count(Faction::Cops);
+    let zombie_count = scenario.count(Faction::Zombies);
+    let human_count = scenario.count(Faction::Civilians);
 
-    println!("Spawning {} entities: {} cops, {} zombies, and {} civilians",
-            entity_count, cop_count, zombie_count, human_count);
+    println!("Spawning {} entities for {}: {} cops, {} zombies, and {} civilians",
+            cop_count + zombie_count + human_count, scenario.name, cop_count, zombie_count, human_count);
 
     let mut state = State {
+        tick: 0,
+        time: 0.0,
         entities: vec!(),
-        buildings: vec!(),
+        buildings: scenario.buildings.clone(),
         building_outlines: vec!(),
+        shelters: scenario.buildings.iter().map(Shelter::for_building).collect(),
+        barricades: vec!(),
+        roads: scenario.roads.clone(),
+        plazas: scenario.plazas.clone(),
+        checkpoints: scenario.checkpoints.clone(),
+        medical_scanner_research_time: scenario.medical_scanner_research_time,
+        visibility_graph: Default::default(),
+        obstacles_changed: false,
         selection: HashSet::new(),
+        control_groups: vec![vec!(); CONTROL_GROUP_COUNT],
         projectiles: vec!(),
-        rng: XorShiftRng::seed_from_u64(random_seed as u64)
+        rng: XorShiftRng::seed_from_u64(random_seed as u64),
+        win_conditions: scenario.win_conditions.clone(),
+        reinforcements: Reinforcements::new(scenario.starting_ammo),
+        statistics: Statistics::new(),
+        new_decals: vec!(),
     };
 
     let entities = &mut state.entities;
-    let buildings = &mut state.buildings;
+    let buildings = &state.buildings;
     let building_outlines = &mut state.building_outlines;
 
-    // We want the spawn area to be proportional to the number of entities
-    // let side_length_of_spawn_area = 3.0 * entity_count_fp.sqrt();
-    let side_length_of_spawn_area = 50.0;
-
-    for i in 0..entity_count {
-        // TODO: need to optimize this later with housing units and two entities shouldn't be placed on same tile
-        let x = state.rng.gen_range(0.0, side_length_of_spawn_area);
-        let y = state.rng.gen_range(0.0, side_length_of_spawn_area);
-        let facing_angle = state.rng.gen_range(0.0, 1 as Scalar);
-        let position = vector2(x, y);
-        let velocity = Vector2::zero();
-
-        let behaviour = if i < cop_count {
-            Behaviour::Cop {
-                rounds_in_magazine: COP_MAGAZINE_CAPACITY,
-                state_stack: vec!()
-            }
-        }
-        else if i < cop_count + zombie_count {
-            Behaviour::Zombie {
-                state: ZombieState::Roaming
-            }
-        }
-        else {
-            Behaviour::Human
-        };
-        entities.push(Entity { position, velocity, facing_angle, behaviour });
+    // Generate outlines around all buildings for building A* pathfinding graphs
+    for building in buildings {
+        building_outlines.push(building.outline(ENTITY_RADIUS * 1.1));
     }
 
-    // Generate some buildings
-    let mut building_x = 0.0;
-    while building_x < 2.0 * side_length_of_spawn_area {
-        let mut building_y = 0.0;
-
-        while building_y < 2.0 * side_length_of_spawn_area {
-            buildings.push(Polygon(vec![
-                Vector2 { x: building_x, y: building_y },
-                Vector2 { x: building_x + 10.0, y: building_y },
-                Vector2 { x: building_x + 10.0, y: building_y + 10.0 },
-                Vector2 { x: building_x, y: building_y + 10.0 }
-            ]));
-
-            building_y += 20.0;
+    for zone in &scenario.spawn_zones {
+        for _ in 0..zone.count {
+            // TODO: two entities shouldn't be placed on same tile
+            // Only spawn in walkable space, clear of the buildings' outlines
+            let mut position = None;
+            for _ in 0..MAX_SPAWN_ATTEMPTS {
+                let x = state.rng.gen_range(zone.min.x, zone.max.x);
+                let y = state.rng.gen_range(zone.min.y, zone.max.y);
+                let candidate = vector2(x, y);
+
+                if !building_outlines.iter().any(|outline| outline.contains_point(candidate)) {
+                    position = Some(candidate);
+                    break;
+                }
+            }
+            // The zone is too built up to find room at random, so squeeze in beside
+            // the nearest building instead
+            let position = match position.or_else(|| walkable_corner_near(building_outlines, 0.5 * (zone.min + zone.max))) {
+                Some(position) => position,
+                None => {
+                    eprintln!("No walkable space for an entity in a spawn zone, skipping it");
+                    continue;
+                }
+            };
+            let facing_angle = state.rng.gen_range(0.0, 1 as Scalar);
+            let velocity = Vector2::zero();
+
+            let behaviour = match zone.faction {
+                Faction::Cops => Behaviour::Cop {
+                    rounds_in_magazine: scenario.starting_ammo.magazine,
+                    magazine_capacity: scenario.starting_ammo.magazine,
+                    rounds_in_reserve: scenario.starting_ammo.reserve,
+                    state_stack: vec!(),
+                    queued_orders: vec!(),
+                },
+                Faction::Zombies => Behaviour::Zombie {
+                    state: ZombieState::Roaming
+                },
+                Faction::Civilians => Behaviour::Human { safe_zone_path: None },
+            };
+            let health = ENTITY_MAX_HEALTH;
+            let incubation_time_remaining = None;
+            let sheltering_in = None;
+            entities.push(Entity { position, velocity, facing_angle, behaviour, health, incubation_time_remaining, sheltering_in });
         }
-
-        building_x += 20.0;
     }
 
-    // Generate outlines around all buildings for building A* pathfinding graphs
-    for i in 0..buildings.len() {
-        let mut outlines = vec!();
-        let norms = buildings[i].normals();
-
-        for j in 0..buildings[i].num_sides() {
-            let norm_sum = norms[j] + norms[if j < 1 { buildings[i].num_sides() - 1 } else { j - 1 }];
-            let offset = norm_sum * ENTITY_RADIUS * 1.1;
-            outlines.push(offset + buildings[i].get(j));
-        }
-
-        building_outlines.push(Polygon(outlines));
-    }
+    state.rebuild_visibility_graph();
 
     state
 }
+
+// The corner of a building outline closest to the point that isn't inside any other
+// outline, if there is one
+fn walkable_corner_near(building_outlines: &Vec<Polygon>, point: Vector2) -> Option<Vector2> {
+    building_outlines.iter()
+        .flat_map(|outline| outline.0.iter().cloned())
+        .filter(|corner| !building_outlines.iter().any(|outline| outline.contains_point(*corner)))
+        .min_by(|a, b| {
+            let distance_a = (*a - point).length_squared();
+            let distance_b = (*b - point).length_squared();
+            distance_a.partial_cmp(&distance_b).unwrap_or(std::cmp::Ordering::Equal)
+        })
+}
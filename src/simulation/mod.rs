@@ -3,4 +3,18 @@ pub mod update;
 pub mod initial_state;
 pub mod control;
 pub mod ai;
-pub mod game_state;
\ No newline at end of file
+pub mod game_state;
+pub mod outcome;
+pub mod timestep;
+pub mod command;
+pub mod replay;
+pub mod spatial_grid;
+pub mod scenario;
+pub mod city_generator;
+pub mod noise;
+pub mod shelter;
+pub mod barricade;
+pub mod reinforcements;
+pub mod statistics;
+pub mod formation;
+pub mod decals;
@@ -0,0 +1,69 @@
+use crate::core::vector::*;
+use crate::core::scalar::*;
+use crate::core::geo::polygon::*;
+
+use super::spatial_grid::SpatialGrid;
+use super::state::*;
+
+// Distance at which a gunshot can be heard in the open
+pub const GUNSHOT_LOUDNESS: Scalar = 40.0;
+
+// Fraction of a noise's loudness left after passing through a building
+const BUILDING_NOISE_ATTENUATION: Scalar = 0.4;
+
+// Something audible that happened during a tick. Unlike a Sound, which is only played
+// back to the player, a noise can be heard by the entities in the simulation
+#[derive(Copy, Clone, Debug)]
+pub struct Noise {
+    pub position: Vector2,
+    // Distance at which the noise can be heard with nothing in the way
+    pub loudness: Scalar,
+}
+
+// Send roaming zombies that hear a noise to investigate where it came from
+pub fn hear_noises(
+    noises: &Vec<Noise>,
+    entities: &mut Vec<Entity>,
+    buildings: &Vec<Polygon>,
+    entity_grid: &SpatialGrid,
+    building_grid: &SpatialGrid) {
+
+    for noise in noises {
+        for i in entity_grid.query(noise.position, noise.loudness) {
+            if entities[i].behaviour != (Behaviour::Zombie { state: ZombieState::Roaming }) {
+                continue;
+            }
+
+            let distance = (entities[i].position - noise.position).length();
+            if distance >= noise.loudness {
+                continue;
+            }
+
+            let buildings_in_the_way = count_buildings_between(
+                buildings, building_grid, noise.position, entities[i].position);
+            let heard_loudness = noise.loudness * BUILDING_NOISE_ATTENUATION.powi(buildings_in_the_way as i32);
+
+            if distance < heard_loudness {
+                entities[i].behaviour = Behaviour::Zombie {
+                    state: ZombieState::Moving { waypoint: noise.position }
+                };
+            }
+        }
+    }
+}
+
+fn count_buildings_between(
+    buildings: &Vec<Polygon>,
+    building_grid: &SpatialGrid,
+    from: Vector2,
+    to: Vector2) -> usize {
+
+    // The grid lists a building once for every cell it overlaps
+    let mut candidates: Vec<usize> = building_grid.query_line(from, to).collect();
+    candidates.sort();
+    candidates.dedup();
+
+    candidates.into_iter()
+        .filter(|&i| buildings[i].num_intersects(from, to) > 0)
+        .count()
+}
@@ -0,0 +1,106 @@
+use crate::core::scalar::Scalar;
+
+use serde::{Serialize, Deserialize};
+
+use super::state::*;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Outcome {
+    HumansWin,
+    ZombiesWin,
+}
+
+// Number of entities with each kind of behaviour
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Census {
+    pub cops: usize,
+    pub civilians: usize,
+    pub zombies: usize,
+    pub dead: usize,
+    // Cops and civilians who will turn, also counted among the cops and civilians
+    pub infected: usize,
+    // Cops and civilians hiding in buildings, also counted among the cops and civilians
+    pub sheltered: usize,
+}
+
+pub fn census(state: &State) -> Census {
+    let mut census = Census { cops: 0, civilians: 0, zombies: 0, dead: 0, infected: 0, sheltered: 0 };
+
+    for entity in &state.entities {
+        match entity.behaviour {
+            Behaviour::Cop { .. } => census.cops += 1,
+            Behaviour::Dead => census.dead += 1,
+            Behaviour::Human { .. } => census.civilians += 1,
+            Behaviour::Zombie { .. } => census.zombies += 1,
+        }
+
+        if entity.behaviour.is_living() && entity.incubation_time_remaining.is_some() {
+            census.infected += 1;
+        }
+        if entity.behaviour.is_living() && entity.sheltering_in.is_some() {
+            census.sheltered += 1;
+        }
+    }
+
+    census
+}
+
+// Number of infected entities the player knows about. With the medical scanner that is
+// all of them, otherwise only those standing at a checkpoint. None if the player has
+// no way of telling who is infected
+pub fn detected_infected(state: &State) -> Option<usize> {
+    let infected = state.entities.iter()
+        .filter(|entity| entity.behaviour.is_living() && entity.incubation_time_remaining.is_some());
+
+    if state.medical_scanner_researched() {
+        Some(infected.count())
+    } else if !state.checkpoints.is_empty() {
+        Some(infected
+            .filter(|entity| state.checkpoints.iter().any(|checkpoint| checkpoint.contains_point(entity.position)))
+            .count())
+    } else {
+        None
+    }
+}
+
+// A way for one side to win the match
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum WinCondition {
+    // The humans win once every zombie is dead
+    ZombiesEliminated,
+    // The humans win by holding out for this many seconds
+    SurviveFor { seconds: Scalar },
+    // The zombies win once every cop and civilian is dead or infected
+    HumansEliminated,
+    // The zombies win once every cop is dead or infected
+    CopsEliminated,
+    // The zombies win once fewer than this many civilians are left
+    CiviliansBelow { count: usize },
+}
+
+// The match is over once any of the state's win conditions has been met
+pub fn check_outcome(state: &State) -> Option<Outcome> {
+    let census = census(state);
+
+    for condition in &state.win_conditions {
+        let outcome = match condition {
+            WinCondition::ZombiesEliminated if census.zombies == 0 =>
+                Some(Outcome::HumansWin),
+            WinCondition::SurviveFor { seconds } if state.time >= *seconds =>
+                Some(Outcome::HumansWin),
+            WinCondition::HumansEliminated if census.cops + census.civilians == 0 =>
+                Some(Outcome::ZombiesWin),
+            WinCondition::CopsEliminated if census.cops == 0 =>
+                Some(Outcome::ZombiesWin),
+            WinCondition::CiviliansBelow { count } if census.civilians < *count =>
+                Some(Outcome::ZombiesWin),
+            _ => None,
+        };
+
+        if outcome.is_some() {
+            return outcome;
+        }
+    }
+
+    None
+}
@@ -0,0 +1,180 @@
+use serde::{Serialize, Deserialize};
+
+use crate::core::vector::*;
+use crate::core::scalar::*;
+use crate::simulation::ai::pathfinding::find_path;
+
+use super::scenario::Ammo;
+use super::state::*;
+use super::update::UpdateArgs;
+
+// Seconds until the first squad can be called in, and between calls after that
+pub const REINFORCEMENT_COOLDOWN: Scalar = 60.0;
+
+// Seconds from calling in a squad to it arriving
+pub const REINFORCEMENT_DELAY: Scalar = 10.0;
+
+pub const SQUAD_SIZE: usize = 4;
+
+// Distance between neighbouring cops of a squad as they arrive, and at the rally point
+const SQUAD_SPACING: Scalar = 1.5;
+
+// How far in from the edge of the map to look for room for a cop to arrive
+const MAX_SPAWN_DEPTH: Scalar = 50.0;
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum MapEdge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+// A squad that has been called in and is on its way
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Squad {
+    pub edge: MapEdge,
+    pub rally_point: Vector2,
+    pub time_until_arrival: Scalar,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Reinforcements {
+    // Seconds until another squad can be called in
+    pub cooldown_remaining: Scalar,
+    pub incoming: Vec<Squad>,
+    // Ammunition each new cop arrives with
+    pub ammo: Ammo,
+}
+
+impl Reinforcements {
+    pub fn new(ammo: Ammo) -> Reinforcements {
+        Reinforcements {
+            cooldown_remaining: REINFORCEMENT_COOLDOWN,
+            incoming: vec!(),
+            ammo,
+        }
+    }
+
+    pub fn ready(&self) -> bool {
+        self.cooldown_remaining <= 0.0
+    }
+
+    // Send a squad in from the edge, if one is available
+    pub fn call(&mut self, edge: MapEdge, rally_point: Vector2) {
+        if !self.ready() {
+            return;
+        }
+        self.cooldown_remaining = REINFORCEMENT_COOLDOWN;
+        self.incoming.push(Squad { edge, rally_point, time_until_arrival: REINFORCEMENT_DELAY });
+    }
+}
+
+// The smallest box around everything on the map
+pub fn map_bounds(state: &State) -> (Vector2, Vector2) {
+    let polygons = state.buildings.iter().chain(state.roads.iter()).chain(state.plazas.iter());
+    let corners = polygons.flat_map(|polygon| polygon.0.iter().cloned())
+        .chain(state.entities.iter().map(|entity| entity.position));
+    bounding_box(corners)
+}
+
+// The smallest box around the buildings, roads and plazas, which stays the same however
+// everyone moves. Maps without any of them fall back to the box around everything
+pub fn static_map_bounds(state: &State) -> (Vector2, Vector2) {
+    let polygons = state.buildings.iter().chain(state.roads.iter()).chain(state.plazas.iter());
+    let (min, max) = bounding_box(polygons.flat_map(|polygon| polygon.0.iter().cloned()));
+    if min.x > max.x {
+        return map_bounds(state);
+    }
+    (min, max)
+}
+
+fn bounding_box<I: Iterator<Item=Vector2>>(points: I) -> (Vector2, Vector2) {
+    let mut min = vector2(INFINITY, INFINITY);
+    let mut max = vector2(-INFINITY, -INFINITY);
+
+    for point in points {
+        min = vector2(min.x.min(point.x), min.y.min(point.y));
+        max = vector2(max.x.max(point.x), max.y.max(point.y));
+    }
+
+    (min, max)
+}
+
+// The edge of the map closest to the point
+pub fn nearest_map_edge(state: &State, point: Vector2) -> MapEdge {
+    let (min, max) = map_bounds(state);
+    let distances = [
+        (max.y - point.y, MapEdge::Top),
+        (point.y - min.y, MapEdge::Bottom),
+        (point.x - min.x, MapEdge::Left),
+        (max.x - point.x, MapEdge::Right),
+    ];
+
+    distances.iter()
+        .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))
+        .unwrap().1
+}
+
+// Count down to the next squad, and bring in the squads that have arrived
+pub fn update_reinforcements(args: &UpdateArgs, state: &mut State) {
+    state.reinforcements.cooldown_remaining = (state.reinforcements.cooldown_remaining - args.dt).max(0.0);
+
+    for squad in &mut state.reinforcements.incoming {
+        squad.time_until_arrival -= args.dt;
+    }
+
+    let (arrived, incoming): (Vec<Squad>, Vec<Squad>) = state.reinforcements.incoming.drain(..)
+        .partition(|squad| squad.time_until_arrival <= 0.0);
+    state.reinforcements.incoming = incoming;
+
+    for squad in arrived {
+        spawn_squad(state, &squad);
+    }
+}
+
+fn spawn_squad(state: &mut State, squad: &Squad) {
+    let (min, max) = map_bounds(state);
+
+    // Come in at the point on the edge closest to the rally point
+    let rally_point = squad.rally_point;
+    let (entry, along, inward) = match squad.edge {
+        MapEdge::Top => (vector2(rally_point.x, max.y), vector2(1.0, 0.0), vector2(0.0, -1.0)),
+        MapEdge::Bottom => (vector2(rally_point.x, min.y), vector2(1.0, 0.0), vector2(0.0, 1.0)),
+        MapEdge::Left => (vector2(min.x, rally_point.y), vector2(0.0, 1.0), vector2(1.0, 0.0)),
+        MapEdge::Right => (vector2(max.x, rally_point.y), vector2(0.0, 1.0), vector2(-1.0, 0.0)),
+    };
+    let entry = vector2(entry.x.max(min.x).min(max.x), entry.y.max(min.y).min(max.y));
+
+    for i in 0..SQUAD_SIZE {
+        let offset = (i as Scalar - 0.5 * (SQUAD_SIZE - 1) as Scalar) * SQUAD_SPACING * along;
+
+        // Step in from the edge until clear of the buildings
+        let mut position = entry + offset;
+        let mut depth = 0.0;
+        while depth < MAX_SPAWN_DEPTH &&
+            state.building_outlines.iter().any(|outline| outline.contains_point(position)) {
+            depth += 1.0;
+            position = entry + offset + depth * inward;
+        }
+
+        let waypoint = rally_point + offset;
+        let path = find_path(position, waypoint, &state.visibility_graph);
+
+        state.entities.push(Entity {
+            position,
+            velocity: Vector2::zero(),
+            facing_angle: inward.angle(),
+            behaviour: Behaviour::Cop {
+                rounds_in_magazine: state.reinforcements.ammo.magazine,
+                magazine_capacity: state.reinforcements.ammo.magazine,
+                rounds_in_reserve: state.reinforcements.ammo.reserve,
+                state_stack: vec![CopState::Moving { waypoint, mode: MoveMode::Moving, path }],
+                queued_orders: vec!(),
+            },
+            health: ENTITY_MAX_HEALTH,
+            incubation_time_remaining: None,
+            sheltering_in: None,
+        });
+    }
+}
@@ -0,0 +1,123 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Serialize, Deserialize};
+
+use super::command::*;
+use super::initial_state::initial_state;
+use super::scenario::Scenario;
+use super::state::State;
+use super::update::*;
+
+// Increment whenever the replay layout or the simulation's response to commands changes
+pub const REPLAY_FORMAT_VERSION: u32 = 1;
+
+pub const LATEST_REPLAY_PATH: &str = "replays/latest.ron";
+
+// Everything needed to reproduce a match: the initial conditions and every command issued
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub scenario: Scenario,
+    pub seed: u32,
+    // Number of ticks the match lasted
+    pub length: u64,
+    pub commands: Vec<TimedCommand>,
+}
+
+#[derive(Deserialize)]
+struct ReplayHeader {
+    version: u32,
+}
+
+impl Replay {
+    pub fn new(scenario: Scenario, seed: u32) -> Replay {
+        Replay {
+            version: REPLAY_FORMAT_VERSION,
+            scenario,
+            seed,
+            length: 0,
+            commands: vec!(),
+        }
+    }
+
+    pub fn initial_state(&self) -> State {
+        initial_state(&self.scenario, self.seed)
+    }
+
+    pub fn record(&mut self, tick: u64, command: Command) {
+        self.commands.push(TimedCommand { tick, command });
+    }
+
+    pub fn save_to_file(&self, path: &str) -> Result<(), String> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| format!("Failed to serialize replay: {}", err))?;
+
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir)
+                .map_err(|err| format!("Failed to create {}: {}", dir.display(), err))?;
+        }
+
+        fs::write(path, contents)
+            .map_err(|err| format!("Failed to write {}: {}", path, err))
+    }
+
+    pub fn load_from_file(path: &str) -> Result<Replay, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read {}: {}", path, err))?;
+
+        let header: ReplayHeader = ron::de::from_str(&contents)
+            .map_err(|err| format!("Failed to parse {}: {}", path, err))?;
+
+        if header.version != REPLAY_FORMAT_VERSION {
+            return Err(format!("Replay {} has version {}, expected version {}",
+                               path, header.version, REPLAY_FORMAT_VERSION));
+        }
+
+        ron::de::from_str(&contents)
+            .map_err(|err| format!("Failed to parse {}: {}", path, err))
+    }
+}
+
+// Plays a replay back by applying its commands at the ticks they were originally issued
+#[derive(Clone)]
+pub struct ReplayPlayer {
+    pub replay: Replay,
+    pub state: State,
+    next_command: usize,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> ReplayPlayer {
+        let state = replay.initial_state();
+        ReplayPlayer {
+            replay,
+            state,
+            next_command: 0,
+        }
+    }
+
+    // Apply the commands due at the current tick, then simulate the tick
+    pub fn step(&mut self, args: &UpdateArgs) -> Vec<Sound> {
+        while let Some(timed) = self.replay.commands.get(self.next_command) {
+            if timed.tick > self.state.tick {
+                break;
+            }
+            apply_command(&mut self.state, &timed.command);
+            self.next_command += 1;
+        }
+
+        update(args, &mut self.state)
+    }
+
+    // Move to the given tick, restarting from the initial state if it lies in the past
+    pub fn seek(&mut self, tick: u64, args: &UpdateArgs) {
+        if tick < self.state.tick {
+            self.state = self.replay.initial_state();
+            self.next_command = 0;
+        }
+        while self.state.tick < tick {
+            self.step(args);
+        }
+    }
+}
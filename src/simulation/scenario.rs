@@ -0,0 +1,227 @@
+use std::fs;
+use std::path::Path;
+
+use crate::core::vector::*;
+use crate::core::scalar::*;
+use crate::core::geo::polygon::*;
+
+use serde::{Serialize, Deserialize};
+
+use super::city_generator::generate_city;
+use super::outcome::WinCondition;
+use super::state::*;
+
+pub const SCENARIO_DIRECTORY: &str = "assets/scenarios";
+
+const PORTION_OF_ENTITIES_COP: Scalar = 0.05;
+const PORTION_OF_ENTITIES_ZOMBIE: Scalar = 0.2;
+
+// Seconds into a generated match until the medical scanner is researched
+const GENERATED_MEDICAL_SCANNER_RESEARCH_TIME: Scalar = 180.0;
+
+// The layout and rules of a match, loaded from a RON file in the scenario directory
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    pub buildings: Vec<Polygon>,
+    // Open ground drawn under everything else. These don't block movement
+    #[serde(default)]
+    pub roads: Vec<Polygon>,
+    #[serde(default)]
+    pub plazas: Vec<Polygon>,
+    // Areas where the cops screen everyone for infection
+    #[serde(default)]
+    pub checkpoints: Vec<Polygon>,
+    // Seconds into the match until the medical scanner is researched, if ever
+    #[serde(default)]
+    pub medical_scanner_research_time: Option<Scalar>,
+    pub spawn_zones: Vec<SpawnZone>,
+    pub starting_ammo: Ammo,
+    // Checked in order, the first one met decides the match
+    pub win_conditions: Vec<WinCondition>,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Faction {
+    Cops,
+    Civilians,
+    Zombies,
+}
+
+// A rectangle in which a number of entities of one faction are placed at random
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SpawnZone {
+    pub faction: Faction,
+    pub count: u32,
+    pub min: Vector2,
+    pub max: Vector2,
+}
+
+// Ammunition each cop starts with
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Ammo {
+    // Rounds the magazine holds, which each cop starts with loaded
+    pub magazine: i64,
+    // Rounds carried for reloading, or None for an unlimited supply
+    pub reserve: Option<i64>,
+}
+
+// Split the entities between the factions in the usual proportions
+fn faction_counts(entity_count: u32) -> (u32, u32, u32) {
+    let entity_count_fp = entity_count as Scalar;
+    let cop_count: u32 = ((entity_count_fp * PORTION_OF_ENTITIES_COP) as u32).max(1);
+    let zombie_count: u32 = ((entity_count_fp * PORTION_OF_ENTITIES_ZOMBIE) as u32).max(1);
+    let human_count: u32 = entity_count - (cop_count + zombie_count);
+    (cop_count, zombie_count, human_count)
+}
+
+impl Scenario {
+    // The original randomly populated grid of buildings
+    pub fn generated(entity_count: u32) -> Scenario {
+        let entity_count_fp = entity_count as Scalar;
+        let (cop_count, zombie_count, human_count) = faction_counts(entity_count);
+
+        // We want the spawn area to be proportional to the number of entities,
+        // but no smaller than the original 50 by 50 area
+        let side_length_of_spawn_area = (5.0 * entity_count_fp.sqrt()).max(50.0);
+        let spawn_zone = |faction, count| SpawnZone {
+            faction,
+            count,
+            min: Vector2::zero(),
+            max: vector2(side_length_of_spawn_area, side_length_of_spawn_area),
+        };
+
+        // Generate some buildings
+        let mut buildings = vec!();
+        let mut building_x = 0.0;
+        while building_x < 2.0 * side_length_of_spawn_area {
+            let mut building_y = 0.0;
+
+            while building_y < 2.0 * side_length_of_spawn_area {
+                buildings.push(Polygon(vec![
+                    Vector2 { x: building_x, y: building_y },
+                    Vector2 { x: building_x + 10.0, y: building_y },
+                    Vector2 { x: building_x + 10.0, y: building_y + 10.0 },
+                    Vector2 { x: building_x, y: building_y + 10.0 }
+                ]));
+
+                building_y += 20.0;
+            }
+
+            building_x += 20.0;
+        }
+
+        Scenario {
+            name: "Grid".to_string(),
+            buildings,
+            roads: vec!(),
+            plazas: vec!(),
+            // The open square between the first four buildings
+            checkpoints: vec![Polygon(vec![
+                vector2(10.0, 10.0),
+                vector2(20.0, 10.0),
+                vector2(20.0, 20.0),
+                vector2(10.0, 20.0),
+            ])],
+            medical_scanner_research_time: Some(GENERATED_MEDICAL_SCANNER_RESEARCH_TIME),
+            spawn_zones: vec![
+                spawn_zone(Faction::Cops, cop_count),
+                spawn_zone(Faction::Zombies, zombie_count),
+                spawn_zone(Faction::Civilians, human_count),
+            ],
+            starting_ammo: Ammo { magazine: COP_MAGAZINE_CAPACITY, reserve: None },
+            win_conditions: vec![WinCondition::ZombiesEliminated, WinCondition::HumansEliminated],
+        }
+    }
+
+    // A procedurally generated city, different for every seed. The cops start out
+    // together in a plaza, which they use as a checkpoint, and everyone else is
+    // spread across the city
+    pub fn generated_city(entity_count: u32, random_seed: u32) -> Scenario {
+        let (cop_count, zombie_count, human_count) = faction_counts(entity_count);
+        let side_length = (10.0 * (entity_count as Scalar).sqrt()).max(100.0);
+        let city = generate_city(side_length, random_seed);
+
+        let (plaza_min, plaza_max) = city.plazas[0].bounding_box();
+        let size = city.size;
+        let city_zone = |faction, count| SpawnZone {
+            faction,
+            count,
+            min: Vector2::zero(),
+            max: vector2(size, size),
+        };
+
+        Scenario {
+            name: format!("Random city #{}", random_seed),
+            buildings: city.buildings,
+            roads: city.roads,
+            checkpoints: vec![city.plazas[0].clone()],
+            plazas: city.plazas,
+            medical_scanner_research_time: Some(GENERATED_MEDICAL_SCANNER_RESEARCH_TIME),
+            spawn_zones: vec![
+                SpawnZone { faction: Faction::Cops, count: cop_count, min: plaza_min, max: plaza_max },
+                city_zone(Faction::Zombies, zombie_count),
+                city_zone(Faction::Civilians, human_count),
+            ],
+            starting_ammo: Ammo { magazine: COP_MAGAZINE_CAPACITY, reserve: None },
+            win_conditions: vec![WinCondition::ZombiesEliminated, WinCondition::HumansEliminated],
+        }
+    }
+
+    pub fn load_from_file(path: &Path) -> Result<Scenario, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+
+        let scenario: Scenario = ron::de::from_str(&contents)
+            .map_err(|err| format!("Failed to parse {}: {}", path.display(), err))?;
+
+        for building in &scenario.buildings {
+            if building.num_sides() < 3 {
+                return Err(format!("Scenario {} has a building with fewer than 3 corners", path.display()));
+            }
+        }
+
+        for zone in &scenario.spawn_zones {
+            if zone.min.x >= zone.max.x || zone.min.y >= zone.max.y {
+                return Err(format!("Scenario {} has a spawn zone with no area", path.display()));
+            }
+        }
+
+        Ok(scenario)
+    }
+
+    pub fn count(&self, faction: Faction) -> u32 {
+        self.spawn_zones.iter()
+            .filter(|zone| zone.faction == faction)
+            .map(|zone| zone.count)
+            .sum()
+    }
+}
+
+// Load every scenario in the directory, sorted by file name.
+// Files that fail to load are reported and skipped
+pub fn load_scenarios(directory: &str) -> Vec<Scenario> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(err) => {
+            eprintln!("Failed to read {}: {}", directory, err);
+            return vec!();
+        }
+    };
+
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |extension| extension == "ron"))
+        .collect();
+    paths.sort();
+
+    let mut scenarios = vec!();
+    for path in paths {
+        match Scenario::load_from_file(&path) {
+            Ok(scenario) => scenarios.push(scenario),
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+    scenarios
+}
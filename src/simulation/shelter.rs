@@ -0,0 +1,107 @@
+use rand::Rng;
+use rand_xorshift::XorShiftRng;
+use serde::{Serialize, Deserialize};
+
+use crate::core::vector::*;
+use crate::core::scalar::*;
+use crate::core::geo::polygon::*;
+
+use super::state::*;
+
+// Floor area each person hiding in a building needs
+const SHELTER_AREA_PER_OCCUPANT: Scalar = 12.0;
+
+pub const DOOR_MAX_HEALTH: Scalar = 100.0;
+
+// Damage a zombie does to a door each second it spends attacking it. A lone zombie
+// takes DOOR_MAX_HEALTH / ZOMBIE_DOOR_DAMAGE_PER_SECOND seconds to break one down
+pub const ZOMBIE_DOOR_DAMAGE_PER_SECOND: Scalar = 5.0;
+
+// How close to a door, centre to centre, a zombie has to be to attack it
+pub const ZOMBIE_DOOR_REACH: Scalar = 3.0 * ENTITY_RADIUS;
+
+// How close to a door someone has to be to go through it
+pub const SHELTER_ENTRY_DISTANCE: Scalar = 2.0 * ENTITY_RADIUS;
+
+// The door and occupants of a building. State keeps one for every building, at the
+// same index as the building
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Shelter {
+    // Point just outside the building where people go in and out
+    pub door: Vector2,
+    // Most people that fit inside at once
+    pub capacity: usize,
+    // Indices of the entities inside, who are taken off the map until they leave
+    // or the door is breached
+    pub occupants: Vec<usize>,
+    // The door is breached on reaching zero, and the building can't be used again
+    pub door_health: Scalar,
+}
+
+impl Shelter {
+    // Every building gets a door in the middle of its longest wall
+    pub fn for_building(building: &Polygon) -> Shelter {
+        let normals = building.normals();
+        let mut longest = 0;
+        let mut longest_length_squared = 0.0;
+        for i in 0..building.num_sides() {
+            let length_squared = (building.get((i + 1) % building.num_sides()) - building.get(i)).length_squared();
+            if length_squared > longest_length_squared {
+                longest = i;
+                longest_length_squared = length_squared;
+            }
+        }
+
+        let middle = 0.5 * (building.get(longest) + building.get((longest + 1) % building.num_sides()));
+        let area = building.signed_area().abs();
+
+        Shelter {
+            door: middle + ENTITY_RADIUS * 1.1 * normals[longest],
+            capacity: ((area / SHELTER_AREA_PER_OCCUPANT) as usize).max(1),
+            occupants: vec!(),
+            door_health: DOOR_MAX_HEALTH,
+        }
+    }
+
+    pub fn is_breached(&self) -> bool {
+        self.door_health <= 0.0
+    }
+
+    pub fn has_room(&self) -> bool {
+        !self.is_breached() && self.occupants.len() < self.capacity
+    }
+}
+
+// Take the entity off the map and into the building
+pub fn enter_shelter(shelters: &mut Vec<Shelter>, entities: &mut Vec<Entity>, building_index: usize, index: usize) {
+    let shelter = &mut shelters[building_index];
+    shelter.occupants.push(index);
+
+    let entity = &mut entities[index];
+    entity.sheltering_in = Some(building_index);
+    entity.position = shelter.door;
+    entity.velocity = Vector2::zero();
+}
+
+// Put the entity back on the map at the door of the building it is in, if any
+pub fn leave_shelter(shelters: &mut Vec<Shelter>, entities: &mut Vec<Entity>, index: usize) {
+    if let Some(building_index) = entities[index].sheltering_in.take() {
+        let shelter = &mut shelters[building_index];
+        shelter.occupants.retain(|&i| i != index);
+        entities[index].position = shelter.door;
+    }
+}
+
+// Break the door down, turning everyone inside out onto the street around it
+pub fn breach_shelter(shelter: &mut Shelter, entities: &mut Vec<Entity>, rng: &mut XorShiftRng) {
+    shelter.door_health = 0.0;
+
+    // Scatter them a little so that they don't all stand on the same spot
+    for index in shelter.occupants.drain(..) {
+        let entity = &mut entities[index];
+        entity.sheltering_in = None;
+        entity.position = shelter.door + vector2(
+            rng.gen_range(-ENTITY_RADIUS, ENTITY_RADIUS),
+            rng.gen_range(-ENTITY_RADIUS, ENTITY_RADIUS));
+    }
+}
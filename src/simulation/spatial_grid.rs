@@ -0,0 +1,210 @@
+use crate::core::scalar::*;
+use crate::core::vector::*;
+use crate::core::geo::polygon::*;
+
+use super::state::*;
+
+// Side length of a cell in the entity grid. Collision queries touch only a few
+// cells, while sight radius queries touch a few hundred cells that are mostly empty
+pub const ENTITY_GRID_CELL_SIZE: Scalar = 4.0;
+
+// Side length of a cell in the building grid, a bit larger than a typical building
+pub const BUILDING_GRID_CELL_SIZE: Scalar = 16.0;
+
+// Bound on the grid's size in each dimension, so that a stray entity far from the
+// rest can't make the grid huge. Anything beyond the bound shares the edge cells
+const MAX_CELLS_PER_SIDE: usize = 1024;
+
+// Uniform grid used to find the entities or buildings near a point without scanning
+// all of them. The grid does not track movement, so it must be rebuilt whenever the
+// entities have moved
+//
+// Each cell lists the indices of everything whose bounding box overlaps the cell.
+// The lists are stored back to back with the cells in row-major order, so the cells
+// of one row that overlap a query are one contiguous slice
+pub struct SpatialGrid {
+    cell_size: Scalar,
+    min: Vector2,
+    columns: usize,
+    rows: usize,
+    // Index into entries of the first item in each cell, plus one past the end
+    cell_starts: Vec<usize>,
+    entries: Vec<usize>,
+}
+
+impl SpatialGrid {
+    fn empty(cell_size: Scalar) -> SpatialGrid {
+        SpatialGrid {
+            cell_size,
+            min: Vector2::zero(),
+            columns: 1,
+            rows: 1,
+            cell_starts: vec![0, 0],
+            entries: vec!(),
+        }
+    }
+
+    // Grid over the living entities
+    pub fn from_entities(entities: &Vec<Entity>) -> SpatialGrid {
+        let mut grid = SpatialGrid::empty(ENTITY_GRID_CELL_SIZE);
+        grid.rebuild_entities(entities);
+        grid
+    }
+
+    // Grid over the polygons' bounding boxes
+    pub fn from_polygons(polygons: &Vec<Polygon>) -> SpatialGrid {
+        let mut grid = SpatialGrid::empty(BUILDING_GRID_CELL_SIZE);
+        let boxes: Vec<(usize, Vector2, Vector2)> = polygons.iter()
+            .enumerate()
+            .map(|(i, polygon)| {
+                let (min, max) = polygon.bounding_box();
+                (i, min, max)
+            })
+            .collect();
+        grid.build(&boxes);
+        grid
+    }
+
+    pub fn rebuild_entities(&mut self, entities: &Vec<Entity>) {
+        // Dead entities and those hiding in buildings are never the subject of a query
+        let boxes: Vec<(usize, Vector2, Vector2)> = entities.iter()
+            .enumerate()
+            .filter(|(_, entity)| entity.behaviour != Behaviour::Dead && entity.sheltering_in.is_none())
+            .map(|(i, entity)| (i, entity.position, entity.position))
+            .collect();
+        self.build(&boxes);
+    }
+
+    fn build(&mut self, boxes: &[(usize, Vector2, Vector2)]) {
+        let mut min = vector2(INFINITY, INFINITY);
+        let mut max = vector2(-INFINITY, -INFINITY);
+        for (_, box_min, box_max) in boxes {
+            min.x = min.x.min(box_min.x);
+            min.y = min.y.min(box_min.y);
+            max.x = max.x.max(box_max.x);
+            max.y = max.y.max(box_max.y);
+        }
+
+        if boxes.is_empty() {
+            // Nothing to store, leave a single empty cell
+            min = Vector2::zero();
+            max = Vector2::zero();
+        }
+
+        let cell_size = self.cell_size;
+        let cells_spanned = |extent: Scalar|
+            ((extent / cell_size) as usize + 1).min(MAX_CELLS_PER_SIDE);
+        self.min = min;
+        self.columns = cells_spanned(max.x - min.x);
+        self.rows = cells_spanned(max.y - min.y);
+
+        // Counting sort of the boxes by the cells they overlap
+        let cell_count = self.columns * self.rows;
+        let mut cell_starts = vec![0; cell_count + 1];
+        for (_, box_min, box_max) in boxes {
+            for cell in self.cells_overlapping(*box_min, *box_max) {
+                cell_starts[cell + 1] += 1;
+            }
+        }
+        for cell in 0..cell_count {
+            cell_starts[cell + 1] += cell_starts[cell];
+        }
+
+        let mut next_entry = cell_starts.clone();
+        let mut entries = vec![0; cell_starts[cell_count]];
+        for (i, box_min, box_max) in boxes {
+            for cell in self.cells_overlapping(*box_min, *box_max) {
+                entries[next_entry[cell]] = *i;
+                next_entry[cell] += 1;
+            }
+        }
+
+        self.cell_starts = cell_starts;
+        self.entries = entries;
+    }
+
+    // Indices of everything that may be within radius of the point. This is a superset
+    // of what is actually in range, so callers still need to check distance. Items
+    // larger than a cell may be listed more than once
+    pub fn query(&self, center: Vector2, radius: Scalar) -> impl Iterator<Item = usize> + '_ {
+        let offset = vector2(radius, radius);
+        self.query_box(center - offset, center + offset)
+    }
+
+    // Indices of everything that may be within radius of the segment
+    pub fn query_segment(&self, p1: Vector2, p2: Vector2, radius: Scalar) -> impl Iterator<Item = usize> + '_ {
+        let offset = vector2(radius, radius);
+        let min = vector2(p1.x.min(p2.x), p1.y.min(p2.y));
+        let max = vector2(p1.x.max(p2.x), p1.y.max(p2.y));
+        self.query_box(min - offset, max + offset)
+    }
+
+    // Indices of everything that may touch the segment, visiting the cells it passes
+    // through in order from p1 to p2. Cheaper than query_segment for long segments,
+    // and lets a search for the first thing hit stop early
+    pub fn query_line(&self, p1: Vector2, p2: Vector2) -> impl Iterator<Item = usize> + '_ {
+        let (_, first_row) = self.cell_coordinates(p1);
+        let (_, last_row) = self.cell_coordinates(p2);
+        let rows: Box<dyn Iterator<Item = usize>> = if first_row <= last_row {
+            Box::new(first_row..last_row + 1)
+        } else {
+            Box::new((last_row..first_row + 1).rev())
+        };
+
+        rows.flat_map(move |row| {
+            // Find the part of the segment within this row. The edge rows also
+            // hold anything beyond the edge of the grid
+            let row_start = self.min.y + row as Scalar * self.cell_size;
+            let row_min_y = if row == 0 { -INFINITY } else { row_start };
+            let row_max_y = if row == self.rows - 1 { INFINITY } else { row_start + self.cell_size };
+            let min_y = row_min_y.max(p1.y.min(p2.y));
+            let max_y = row_max_y.min(p1.y.max(p2.y));
+
+            let (mut min_x, mut max_x) = if p1.y == p2.y {
+                (p1.x, p2.x)
+            } else {
+                let x_at = |y: Scalar| p1.x + (y - p1.y) * (p2.x - p1.x) / (p2.y - p1.y);
+                (x_at(min_y), x_at(max_y))
+            };
+            if min_x > max_x {
+                std::mem::swap(&mut min_x, &mut max_x);
+            }
+
+            // Allow for rounding error where the segment crosses a cell boundary
+            const PADDING: Scalar = 1e-6;
+            let (min_column, _) = self.cell_coordinates(vector2(min_x - PADDING, min_y));
+            let (max_column, _) = self.cell_coordinates(vector2(max_x + PADDING, min_y));
+
+            let start = self.cell_starts[row * self.columns + min_column];
+            let end = self.cell_starts[row * self.columns + max_column + 1];
+            self.entries[start..end].iter().cloned()
+        })
+    }
+
+    fn query_box(&self, min: Vector2, max: Vector2) -> impl Iterator<Item = usize> + '_ {
+        let (min_column, min_row) = self.cell_coordinates(min);
+        let (max_column, max_row) = self.cell_coordinates(max);
+
+        (min_row..max_row + 1).flat_map(move |row| {
+            let start = self.cell_starts[row * self.columns + min_column];
+            let end = self.cell_starts[row * self.columns + max_column + 1];
+            self.entries[start..end].iter().cloned()
+        })
+    }
+
+    fn cells_overlapping(&self, min: Vector2, max: Vector2) -> impl Iterator<Item = usize> {
+        let (min_column, min_row) = self.cell_coordinates(min);
+        let (max_column, max_row) = self.cell_coordinates(max);
+        let columns = self.columns;
+
+        (min_row..max_row + 1).flat_map(move |row|
+            (min_column..max_column + 1).map(move |column| row * columns + column))
+    }
+
+    fn cell_coordinates(&self, position: Vector2) -> (usize, usize) {
+        let to_cell = |offset: Scalar, cells: usize|
+            ((offset / self.cell_size).max(0.0) as usize).min(cells - 1);
+        (to_cell(position.x - self.min.x, self.columns),
+         to_cell(position.y - self.min.y, self.rows))
+    }
+}
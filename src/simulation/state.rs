@@ -3,28 +3,115 @@ use crate::core::scalar::Scalar;
 use crate::core::geo::polygon::*;
 
 use crate::simulation::ai::path::Path;
+use crate::simulation::ai::visibility_graph::VisibilityGraph;
+use crate::simulation::outcome::WinCondition;
+use crate::simulation::shelter::Shelter;
+use crate::simulation::barricade::Barricade;
+use crate::simulation::reinforcements::Reinforcements;
+use crate::simulation::statistics::Statistics;
+use crate::simulation::decals::Decal;
 
 use std::collections::HashSet;
+use std::sync::Arc;
+use serde::{Serialize, Deserialize};
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct State {
+    // Number of ticks simulated so far
+    pub tick: u64,
+    // Simulated time so far, in seconds
+    pub time: Scalar,
     pub entities: Vec<Entity>,
     pub buildings: Vec<Polygon>,
     pub building_outlines: Vec<Polygon>,
+    // The door and occupants of each building, at the same index as the building
+    pub shelters: Vec<Shelter>,
+    // Every barricade the cops have started on, including those since destroyed
+    pub barricades: Vec<Barricade>,
+    // Open ground, only drawn
+    pub roads: Vec<Polygon>,
+    pub plazas: Vec<Polygon>,
+    // Areas where the cops screen everyone for infection
+    pub checkpoints: Vec<Polygon>,
+    // Time into the match at which the medical scanner is researched, letting the player
+    // see every infected entity. None if it never is
+    pub medical_scanner_research_time: Option<Scalar>,
+    // Derived from the buildings and barricades, so it isn't saved. Call
+    // rebuild_visibility_graph after loading or changing them
+    #[serde(skip)]
+    pub visibility_graph: Arc<VisibilityGraph>,
+    // Set when a barricade is finished or destroyed, so that the visibility graph
+    // gets rebuilt at the end of the tick
+    #[serde(skip)]
+    pub obstacles_changed: bool,
     pub selection: HashSet<usize>,
+    // Cops assigned to each control group, in order of index. Those who die or turn
+    // are taken out at the end of the tick
+    pub control_groups: Vec<Vec<usize>>,
     pub projectiles: Vec<Projectile>,
     pub rng: rand_xorshift::XorShiftRng,
+    pub win_conditions: Vec<WinCondition>,
+    pub reinforcements: Reinforcements,
+    pub statistics: Statistics,
+    // Blood and scorch marks left on the ground over the last tick, for the presentation
+    // to collect. Cleared at the start of every tick
+    #[serde(skip)]
+    pub new_decals: Vec<Decal>,
+}
+
+impl State {
+    pub fn rebuild_visibility_graph(&mut self) {
+        let mut obstacles = self.buildings.clone();
+        let mut outlines = self.building_outlines.clone();
+        for barricade in self.barricades.iter().filter(|barricade| barricade.is_standing()) {
+            let polygon = barricade.polygon();
+            outlines.push(polygon.outline(ENTITY_RADIUS * 1.1));
+            obstacles.push(polygon);
+        }
+
+        self.visibility_graph = Arc::new(VisibilityGraph::new(&self.buildings, obstacles, &outlines));
+        self.obstacles_changed = false;
+    }
+
+    // The cops in the control group, if there is such a group
+    pub fn control_group(&self, group: usize) -> &[usize] {
+        self.control_groups.get(group).map_or(&[], |members| &members[..])
+    }
+
+    pub fn entity_positions(&self) -> Vec<Vector2> {
+        self.entities.iter().map(|entity| entity.position).collect()
+    }
+
+    pub fn medical_scanner_researched(&self) -> bool {
+        self.medical_scanner_research_time.map_or(false, |research_time| self.time >= research_time)
+    }
 }
 
 pub const ENTITY_RADIUS: Scalar = 0.5;
+
+// Number of control groups, bound to the keys 1 to 9
+pub const CONTROL_GROUP_COUNT: usize = 9;
 pub const ENTITY_DRAG: Scalar = 1.0;
 
-#[derive(Clone)]
+pub const ENTITY_MAX_HEALTH: Scalar = 100.0;
+
+// Fraction of their normal speed that an entity on the brink of death can still manage
+pub const WOUNDED_MIN_SPEED_MULTIPLIER: Scalar = 0.4;
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Entity {
     pub position: Vector2,
     pub velocity: Vector2,
     pub facing_angle: Scalar,
     pub behaviour: Behaviour,
+    // Dies on reaching zero
+    pub health: Scalar,
+    // Seconds left until an infected entity turns into a zombie, or None if not infected.
+    // Until then, infected cops and civilians carry on as normal
+    pub incubation_time_remaining: Option<Scalar>,
+    // Index of the building the entity is hiding in. While inside, the entity is off the
+    // map: it stays at the door, and can't be seen, bitten or shot
+    pub sheltering_in: Option<usize>,
 }
 
 impl Entity {
@@ -44,7 +131,12 @@ impl Entity {
     }
     pub fn accelerate_along_vector(&mut self, vector: Vector2, delta_time: Scalar, force: Scalar) {
         self.look_along_vector(vector, delta_time);
-        self.velocity += delta_time * force * vector.normalize();
+        self.velocity += delta_time * force * self.speed_multiplier() * vector.normalize();
+    }
+    // Wounded entities move slower, in proportion to how badly they are hurt
+    pub fn speed_multiplier(&self) -> Scalar {
+        let health_fraction = (self.health / ENTITY_MAX_HEALTH).max(0.0).min(1.0);
+        WOUNDED_MIN_SPEED_MULTIPLIER + (1.0 - WOUNDED_MIN_SPEED_MULTIPLIER) * health_fraction
     }
 }
 
@@ -62,37 +154,99 @@ pub const COP_ANGULAR_ACCURACY_STD_DEV: Scalar = 0.1;
 
 pub const COP_MAGAZINE_CAPACITY: i64 = 6;
 
+// Distance between a cop and a zombie, centre to centre, at which the cop can hit it with a baton
+pub const COP_MELEE_REACH: Scalar = 3.0 * ENTITY_RADIUS;
+
+// A cop with no ammo left only goes after zombies this close
+pub const COP_MELEE_CHARGE_RADIUS: Scalar = 8.0;
+
+// Time to ready the first swing, and between swings after that
+pub const COP_MELEE_WINDUP_TIME: Scalar = 0.3;
+pub const COP_MELEE_SWING_COOLDOWN: Scalar = 0.8;
+
+// Chance that a swing kills the zombie outright, and otherwise that it stuns it
+pub const BATON_KILL_CHANCE: Scalar = 0.2;
+pub const BATON_STUN_CHANCE: Scalar = 0.5;
+pub const BATON_STUN_TIME: Scalar = 1.5;
+
+// Speed at which a zombie hit by a baton is knocked away from the cop
+pub const BATON_KNOCKBACK_SPEED: Scalar = 4.0;
+
 pub const ZOMBIE_SIGHT_RADIUS: f64 = 30.0;
 pub const ZOMBIE_SIGHT_RADIUS_SQUARE: f64 = ZOMBIE_SIGHT_RADIUS * ZOMBIE_SIGHT_RADIUS;
 
+// Zombies can smell people hiding in a building from this far from its door
+pub const ZOMBIE_SCENT_RADIUS: Scalar = 10.0;
+
 pub const HUMAN_SIGHT_RADIUS: f64 = 40.0;
 pub const HUMAN_SIGHT_RADIUS_SQUARE: f64 = HUMAN_SIGHT_RADIUS * HUMAN_SIGHT_RADIUS;
 
+// Civilians try to keep this far apart from the people around them
+pub const HUMAN_SEPARATION_RADIUS: Scalar = 1.5;
+
+// Civilians fleeing together stick with the others within this distance
+pub const HUMAN_COHESION_RADIUS: Scalar = 6.0;
+
+// Civilians steer away from walls closer than this
+pub const HUMAN_WALL_AVOIDANCE_DISTANCE: Scalar = 2.0;
+
+// Fleeing civilians run for the door of any building with room inside within this distance
+pub const HUMAN_SHELTER_SEEK_RADIUS: Scalar = 15.0;
+
+// How much each urge counts for when a civilian decides which way to go
+pub const HUMAN_FLEE_WEIGHT: Scalar = 1.0;
+pub const HUMAN_SHELTER_WEIGHT: Scalar = 1.2;
+pub const HUMAN_SAFE_ZONE_WEIGHT: Scalar = 0.8;
+pub const HUMAN_SEEK_COPS_WEIGHT: Scalar = 0.5;
+pub const HUMAN_COHESION_WEIGHT: Scalar = 0.2;
+pub const HUMAN_SEPARATION_WEIGHT: Scalar = 0.5;
+pub const HUMAN_WALL_AVOIDANCE_WEIGHT: Scalar = 1.5;
+
 pub const COP_SIGHT_RADIUS: f64 = 50.0;
 pub const COP_SIGHT_RADIUS_SQUARE: f64 = COP_SIGHT_RADIUS * COP_SIGHT_RADIUS;
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub enum Behaviour {
     Cop {
         rounds_in_magazine: i64,
+        // Rounds the magazine holds when full
+        magazine_capacity: i64,
+        // Rounds left for reloading, or None for an unlimited supply
+        rounds_in_reserve: Option<i64>,
         // state: CopState
 
         // A stack of the cop's states
         // - The current state is the state on top of the stack
         // - When the cop finishes with a state that state is popped from the stack
         // - If there are no states in the stack, the cop is idle
-        state_stack: Vec<CopState>
+        state_stack: Vec<CopState>,
+        // Orders the player queued up with shift, oldest first. The next one starts
+        // whenever the cop is idle
+        queued_orders: Vec<CopState>,
     },
     Dead,
-    Human,
+    Human {
+        // Route to the nearest safe zone, while fleeing from zombies
+        safe_zone_path: Option<Path>
+    },
     Zombie {
         state: ZombieState
     }
 }
 
+impl Behaviour {
+    // Cops and civilians, as opposed to zombies and the dead
+    pub fn is_living(&self) -> bool {
+        match self {
+            Behaviour::Cop { .. } | Behaviour::Human { .. } => true,
+            _ => false
+        }
+    }
+}
+
 pub const COP_MIN_DISTANCE_FROM_WAYPOINT_SQUARED: Scalar = 0.2;
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum CopState {
     Aiming {
         aim_time_remaining: Scalar,
@@ -110,9 +264,37 @@ pub enum CopState {
         target_index: usize,
         path: Option<Path>,
     },
+    // Fighting a zombie with a baton, closing in first if it is out of reach
+    Melee {
+        target_index: usize,
+        swing_cooldown_remaining: Scalar,
+    },
+    // Working on a barricade, after getting to it
+    Building {
+        barricade_index: usize,
+        path: Option<Path>,
+    },
+    // Heading for the door of a building to hold out inside
+    Garrisoning {
+        building_index: usize,
+        path: Option<Path>,
+    },
+    // Walking a loop through the waypoints, heading for the one at index next,
+    // and stopping to deal with any zombie seen on the way
+    Patrolling {
+        waypoints: Vec<Vector2>,
+        next: usize,
+        path: Option<Path>,
+    },
+    // Holding position at the anchor, engaging any zombie in sight and coming back
+    // afterwards
+    Guarding {
+        anchor: Vector2,
+        path: Option<Path>,
+    },
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub enum ZombieState {
     Chasing {
         target_index: usize
@@ -120,31 +302,63 @@ pub enum ZombieState {
     Moving {
         waypoint: Vector2
     },
-    Roaming
+    Roaming,
+    // Trying to break down the door of a building with people hiding inside
+    AttackingDoor {
+        building_index: usize
+    },
+    // Knocked senseless by a baton, unable to do anything for a while
+    Stunned {
+        stun_time_remaining: Scalar
+    },
 }
 
 pub const PROJECTILE_DRAG: Scalar = 1.0;
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Projectile {
     pub position: Vector2,
     pub velocity: Vector2,
     pub kind: ProjectileKind,
+    // Index of the cop who fired it, so kills can be credited
+    pub shooter: Option<usize>,
 }
 
 pub const BULLET_RADIUS: Scalar = 0.12;
 pub const BULLET_SPEED: Scalar = 40.0;
 pub const BULLET_SPEED_MIN: Scalar = 10.0;
+
+// Damage done by a bullet at full speed hitting the torso. Bullets slow down as they
+// travel, and do proportionally less damage the slower they are going
+pub const BULLET_DAMAGE: Scalar = 120.0;
+
+// How far from the middle of an entity a bullet has to pass, as a fraction of the
+// entity's radius, to hit something other than a vital organ or the torso
+pub const BULLET_VITAL_HIT_OFFSET: Scalar = 0.25;
+pub const BULLET_LIMB_HIT_OFFSET: Scalar = 0.65;
+
+pub const BULLET_VITAL_DAMAGE_MULTIPLIER: Scalar = 2.0;
+pub const BULLET_LIMB_DAMAGE_MULTIPLIER: Scalar = 0.5;
+
+// Damage per second a zombie does to someone it is biting
+pub const ZOMBIE_BITE_DAMAGE_PER_SECOND: Scalar = 40.0;
+
+// Chance per second of being bitten that the victim fails to resist the infection
+pub const BITE_INFECTION_CHANCE_PER_SECOND: Scalar = 0.3;
+
+// Range of the time between being infected and turning into a zombie
+pub const INCUBATION_TIME_MIN: Scalar = 15.0;
+pub const INCUBATION_TIME_MAX: Scalar = 60.0;
 pub const BULLET_SPAWN_DISTANCE_MULTIPLIER: Scalar = 1.25;
 pub const CASING_SPEED: Scalar = 1.0;
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ProjectileKind {
     Bullet,
     Casing,
 }
 
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum MoveMode {
     Moving,
     Sprinting
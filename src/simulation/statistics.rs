@@ -0,0 +1,110 @@
+use std::collections::BTreeMap;
+
+use serde::{Serialize, Deserialize};
+
+use crate::core::scalar::*;
+
+use super::state::*;
+
+const POINTS_PER_ZOMBIE_KILLED: i64 = 100;
+const POINTS_PER_CIVILIAN_SAVED: i64 = 200;
+const POINTS_PER_CIVILIAN_LOST: i64 = -100;
+
+// Awarded in proportion to accuracy, so hitting with every shot earns all of it
+const POINTS_FOR_PERFECT_ACCURACY: i64 = 1000;
+
+// Winning sooner than this many seconds into the match earns points for every second
+// to spare
+const VICTORY_TIME_BONUS_SECONDS: Scalar = 600.0;
+const POINTS_PER_SECOND_TO_SPARE: Scalar = 5.0;
+
+// Running totals kept by update over the course of a match
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Statistics {
+    pub shots_fired: u32,
+    // Bullets that hit a zombie. Hitting anyone else isn't something to be proud of
+    pub hits: u32,
+    // Civilians killed or turned
+    pub civilians_lost: u32,
+    // Zombies killed by each cop, by gun or baton, keyed by the cop's index
+    pub zombies_killed: BTreeMap<usize, u32>,
+    // Time into the match at which the humans won, if they did
+    pub time_to_victory: Option<Scalar>,
+}
+
+impl Statistics {
+    pub fn new() -> Statistics {
+        Statistics::default()
+    }
+
+    pub fn record_kill(&mut self, cop_index: usize) {
+        *self.zombies_killed.entry(cop_index).or_insert(0) += 1;
+    }
+
+    pub fn total_zombies_killed(&self) -> u32 {
+        self.zombies_killed.values().sum()
+    }
+
+    // Fraction of shots that hit a zombie, or None before the first shot
+    pub fn accuracy(&self) -> Option<Scalar> {
+        if self.shots_fired == 0 {
+            None
+        } else {
+            Some(self.hits as Scalar / self.shots_fired as Scalar)
+        }
+    }
+}
+
+// Civilians still alive and free of infection
+pub fn civilians_saved(state: &State) -> u32 {
+    state.entities.iter()
+        .filter(|entity| match entity.behaviour {
+            Behaviour::Human { .. } => entity.incubation_time_remaining.is_none(),
+            _ => false,
+        })
+        .count() as u32
+}
+
+// Points earned for each part of a match
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Score {
+    pub zombies_killed: i64,
+    pub civilians_saved: i64,
+    pub civilians_lost: i64,
+    pub accuracy: i64,
+    pub time_bonus: i64,
+}
+
+impl Score {
+    pub fn total(&self) -> i64 {
+        (self.zombies_killed + self.civilians_saved + self.civilians_lost + self.accuracy + self.time_bonus).max(0)
+    }
+
+    // Name and points of each part, in the order they are shown
+    pub fn breakdown(&self) -> Vec<(&'static str, i64)> {
+        vec![
+            ("Zombies killed", self.zombies_killed),
+            ("Civilians saved", self.civilians_saved),
+            ("Civilians lost", self.civilians_lost),
+            ("Accuracy", self.accuracy),
+            ("Time bonus", self.time_bonus),
+        ]
+    }
+}
+
+pub fn score(state: &State) -> Score {
+    let statistics = &state.statistics;
+
+    let time_bonus = match statistics.time_to_victory {
+        Some(time) => ((VICTORY_TIME_BONUS_SECONDS - time).max(0.0) * POINTS_PER_SECOND_TO_SPARE) as i64,
+        None => 0,
+    };
+
+    Score {
+        zombies_killed: statistics.total_zombies_killed() as i64 * POINTS_PER_ZOMBIE_KILLED,
+        civilians_saved: civilians_saved(state) as i64 * POINTS_PER_CIVILIAN_SAVED,
+        civilians_lost: statistics.civilians_lost as i64 * POINTS_PER_CIVILIAN_LOST,
+        accuracy: (statistics.accuracy().unwrap_or(0.0) * POINTS_FOR_PERFECT_ACCURACY as Scalar) as i64,
+        time_bonus,
+    }
+}
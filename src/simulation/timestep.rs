@@ -0,0 +1,48 @@
+use crate::core::scalar::Scalar;
+
+// The simulation always advances in ticks of this length, regardless of frame rate,
+// so that identical seeds and inputs produce identical matches on any machine
+pub const TICK_RATE: Scalar = 60.0;
+pub const TIMESTEP: Scalar = 1.0 / TICK_RATE;
+
+// Cap on the number of ticks simulated in a single frame, so that one slow frame
+// can't cause a cascade of ever slower frames
+pub const MAX_TICKS_PER_FRAME: usize = 8;
+
+#[derive(Clone)]
+pub struct FixedTimestep {
+    accumulator: Scalar,
+    max_ticks_per_frame: usize,
+}
+
+impl FixedTimestep {
+    pub fn new() -> FixedTimestep {
+        FixedTimestep::with_max_ticks_per_frame(MAX_TICKS_PER_FRAME)
+    }
+
+    // For callers that deliberately run more ticks per frame than real time, such as
+    // a sped up replay
+    pub fn with_max_ticks_per_frame(max_ticks_per_frame: usize) -> FixedTimestep {
+        FixedTimestep { accumulator: 0.0, max_ticks_per_frame }
+    }
+
+    // Accumulate the frame time and return the whole number of ticks to simulate
+    pub fn advance(&mut self, delta_time: Scalar) -> usize {
+        self.accumulator += delta_time;
+
+        let ticks = (self.accumulator / TIMESTEP) as usize;
+        if ticks > self.max_ticks_per_frame {
+            // Drop the time we can't catch up on rather than carrying it forward
+            self.accumulator = 0.0;
+            self.max_ticks_per_frame
+        } else {
+            self.accumulator -= ticks as Scalar * TIMESTEP;
+            ticks
+        }
+    }
+
+    // How far we are between the last tick and the next one, in the range [0, 1)
+    pub fn alpha(&self) -> Scalar {
+        self.accumulator / TIMESTEP
+    }
+}
@@ -1,11 +1,22 @@
 use rand::distributions::*;
+use rand::Rng;
+use rand_xorshift::XorShiftRng;
 
 use crate::core::geo::circle::*;
 use crate::core::geo::intersect::segment_circle::*;
 use crate::core::geo::polygon::*;
 use crate::core::geo::segment2::*;
 
-use crate::simulation::ai::pathfinding::find_path;
+use crate::simulation::ai::path::Path;
+use crate::simulation::ai::pathfinding::{can_see, find_path};
+use crate::simulation::ai::visibility_graph::VisibilityGraph;
+use crate::simulation::noise::{Noise, GUNSHOT_LOUDNESS, hear_noises};
+use crate::simulation::shelter::*;
+use crate::simulation::barricade::*;
+use crate::simulation::reinforcements::update_reinforcements;
+use crate::simulation::spatial_grid::SpatialGrid;
+use crate::simulation::statistics::Statistics;
+use crate::simulation::decals::*;
 use crate::simulation::state::MoveMode;
 
 use super::state::*;
@@ -25,20 +36,39 @@ pub struct UpdateArgs {
 pub fn update(args: &UpdateArgs, state: &mut State) -> Vec<Sound> {
 
     let mut sounds = vec!();
+    let mut noises = vec!();
 
     const DOUBLE_ENTITY_RADIUS_SQUARED: f64 = 4.0 * ENTITY_RADIUS * ENTITY_RADIUS;
 
+    // Marks left last tick have had their chance to be collected
+    state.new_decals.clear();
+
+    // Squads arriving this tick join in straight away
+    update_reinforcements(args, state);
+
+    // Broadphase grids for finding nearby entities and buildings. Buildings don't
+    // move, so their grid is kept with the visibility graph
+    let mut entity_grid = SpatialGrid::from_entities(&state.entities);
+    let visibility_graph = state.visibility_graph.clone();
+    let building_grid = &visibility_graph.building_grid;
+
     // Check for collisions
     for i in 0..state.entities.len() {
         let p1 = state.entities[i].position;
         let circle = Circle { center: p1, radius: ENTITY_RADIUS };
 
-        if state.entities[i].behaviour == Behaviour::Dead {
+        // The dead and those hiding in buildings don't collide with anything
+        if state.entities[i].behaviour == Behaviour::Dead || state.entities[i].sheltering_in.is_some() {
             continue;
         }
 
         // Collisions with other entities
-        for j in (i + 1)..state.entities.len() {
+        for j in entity_grid.query(p1, 2.0 * ENTITY_RADIUS) {
+
+            // Only handle each pair once
+            if j <= i {
+                continue;
+            }
 
             // Do not collide with dead entities
             if state.entities[j].behaviour == Behaviour::Dead {
@@ -52,66 +82,115 @@ pub fn update(args: &UpdateArgs, state: &mut State) -> Vec<Sound> {
             let delta_length_squared = delta.length_squared();
 
             if delta_length_squared < DOUBLE_ENTITY_RADIUS_SQUARED {
-                handle_collision(args, &mut state.entities, i, j, &delta, delta_length_squared, &mut sounds);
+                handle_collision(args, &mut state.entities, &mut state.rng, &mut state.statistics, &mut state.new_decals, i, j, &delta, delta_length_squared, &mut sounds);
             }
         }
 
         // Collisions with buildings
-        for j in 0..state.buildings.len() {
-            // Check if position is inside the building
-            let mut overlap = state.buildings[j].contains_point(p1);
-            let inside = overlap;
-
-            // Don't bother doing this if we already know there's an overlap
-            if !inside {
-                // Check if one of the building's sides intersects the entity
-                for k in 0..state.buildings[j].num_sides() {
-                    let segment = Segment2 {
-                        p1: state.buildings[j].get(k),
-                        p2: state.buildings[j].get((k + 1) % state.buildings[j].num_sides())
-                    };
-
-                    if segment_circle_has_intersection(&segment, &circle) {
-                        overlap = true;
-                        break;
-                    }
-                }
-            }
-
-            if overlap {
+        for j in building_grid.query(p1, ENTITY_RADIUS) {
+            if let Some(inside) = polygon_overlap(&state.buildings[j], &circle) {
                 handle_building_collision(args, &mut state.entities[i], &state.buildings[j], inside);
                 break;
             }
         }
+
+        // Collisions with barricades, which zombies pushing against them wear down.
+        // There are few enough of them to check them all
+        for barricade in &mut state.barricades {
+            const BARRICADE_REACH: Scalar = ENTITY_RADIUS + 0.5 * BARRICADE_THICKNESS;
+            if !barricade.is_standing() || barricade.segment().dist_squared(p1) > BARRICADE_REACH * BARRICADE_REACH {
+                continue;
+            }
+
+            let polygon = barricade.polygon();
+            if let Some(inside) = polygon_overlap(&polygon, &circle) {
+                handle_building_collision(args, &mut state.entities[i], &polygon, inside);
+
+                if let Behaviour::Zombie { .. } = state.entities[i].behaviour {
+                    barricade.health -= ZOMBIE_BARRICADE_DAMAGE_PER_SECOND * args.dt;
+                    if barricade.is_destroyed() {
+                        state.obstacles_changed = true;
+                    }
+                }
+            }
+        }
     }
 
     // Apply individual behaviours
     for i in 0..state.entities.len() {
         match &state.entities[i].behaviour {
             Behaviour::Cop { .. } =>
-                update_cop(&args, state, i, &mut sounds),
+            // Garrisoned cops wait inside until they are ordered out or the door is breached
+                if state.entities[i].sheltering_in.is_none() {
+                    update_cop(&args, state, &entity_grid, building_grid, i, &mut sounds, &mut noises)
+                },
             Behaviour::Dead =>
             // Do nothing
                 (),
-            Behaviour::Human =>
-            // Run from zombies!
-                simulate_human(args, &mut state.entities, &state.buildings, i),
+            Behaviour::Human { .. } =>
+            // Run from zombies, unless already safe inside
+                if state.entities[i].sheltering_in.is_none() {
+                    simulate_human(args, state, &entity_grid, building_grid, i)
+                },
             b @ Behaviour::Zombie { .. } => {
                 // Chase humans and cops!
 //                simulate_zombie(args, state, i)
-                let behaviour = update_zombie(&args, state, i, b.clone());
+                let behaviour = update_zombie(&args, state, &entity_grid, building_grid, i, b.clone());
                 state.entities[i].behaviour = behaviour;
             }
         }
     }
 
-    // Apply acceleration
+    // The infected turn once their incubation period is over
+    for i in 0..state.entities.len() {
+        let entity = &mut state.entities[i];
+        if !entity.behaviour.is_living() {
+            continue;
+        }
+        if let Some(time_remaining) = entity.incubation_time_remaining {
+            let time_remaining = time_remaining - args.dt;
+            if time_remaining > 0.0 {
+                entity.incubation_time_remaining = Some(time_remaining);
+            } else {
+                turn_into_zombie(entity, &mut state.statistics, &mut state.new_decals, &mut sounds);
+
+                // Turning inside a building lets everyone out, along with the new zombie
+                if let Some(building_index) = entity.sheltering_in {
+                    breach_shelter(&mut state.shelters[building_index], &mut state.entities, &mut state.rng);
+                }
+            }
+        }
+    }
+
+    // Zombies that heard gunfire come to investigate
+    hear_noises(&noises, &mut state.entities, &state.buildings, &entity_grid, building_grid);
+
+    // Apply acceleration. Those hiding in buildings stay where they are
     for e in &mut state.entities {
+        if e.sheltering_in.is_some() {
+            e.velocity = Vector2::zero();
+            continue;
+        }
         let displacement = args.dt * e.velocity;
         e.position += displacement;
         e.velocity -= ENTITY_DRAG * displacement;
     }
 
+    // The badly wounded leave a trail of blood wherever they go. Each entity marks the
+    // ground on a different tick, so the trails don't all appear at once
+    for (i, e) in state.entities.iter().enumerate() {
+        if (state.tick + i as u64) % DRAG_TRAIL_INTERVAL != 0 || e.behaviour == Behaviour::Dead ||
+            e.sheltering_in.is_some() || e.health >= DRAG_TRAIL_HEALTH {
+            continue;
+        }
+        if e.velocity.length_squared() > DRAG_TRAIL_MIN_SPEED * DRAG_TRAIL_MIN_SPEED {
+            state.new_decals.push(Decal { kind: DecalKind::DragTrail, position: e.position, angle: e.velocity.angle(), radius: 0.25 });
+        }
+    }
+
+    // Everyone has moved, so the grid needs rebuilding before checking for bullet hits
+    entity_grid.rebuild_entities(&state.entities);
+
     // Remove motionless bullets
     state.projectiles.retain(
         |p| p.kind != ProjectileKind::Bullet ||
@@ -133,7 +212,7 @@ pub fn update(args: &UpdateArgs, state: &mut State) -> Vec<Sound> {
         }
 
         let mut first_intersect_time_and_index = None;
-        for i in 0..state.entities.len() {
+        for i in entity_grid.query_segment(segment.p1, segment.p2, ENTITY_RADIUS) {
             let entity = &state.entities[i];
 
             if entity.behaviour == Behaviour::Dead {
@@ -163,7 +242,10 @@ pub fn update(args: &UpdateArgs, state: &mut State) -> Vec<Sound> {
             None => ()
         }
 
-        if !can_see(&state.buildings, segment.p1, segment.p2) {
+        if !can_see(&state.buildings, building_grid, segment.p1, segment.p2) {
+            // A bullet goes less than a unit each tick, so where it was is close enough
+            // to the wall to mark
+            state.new_decals.push(Decal { kind: DecalKind::Scorch, position: segment.p1, angle: p.velocity.angle(), radius: 0.2 });
             first_intersect_time_and_index = None;
             p.velocity = Vector2::zero();
         }
@@ -171,36 +253,78 @@ pub fn update(args: &UpdateArgs, state: &mut State) -> Vec<Sound> {
         match first_intersect_time_and_index {
             None => (),
             Some((_, i)) => {
-                state.entities[i].behaviour = Behaviour::Dead;
+                let entity = &mut state.entities[i];
+                let is_zombie = match entity.behaviour {
+                    Behaviour::Zombie { .. } => true,
+                    _ => false,
+                };
+                if is_zombie {
+                    state.statistics.hits += 1;
+                }
+
+                entity.health -= bullet_damage(p.velocity.length(), &segment, entity.position);
+
+                // Blood sprays out behind whoever was hit
+                let direction = p.velocity.normalize();
+                state.new_decals.push(Decal {
+                    kind: DecalKind::BloodSplat,
+                    position: entity.position + ENTITY_RADIUS * direction,
+                    angle: direction.angle(),
+                    radius: 0.5
+                });
+                if entity.health <= 0.0 {
+                    match (&entity.behaviour, p.shooter) {
+                        (Behaviour::Zombie { .. }, Some(shooter)) => state.statistics.record_kill(shooter),
+                        (Behaviour::Human { .. }, _) => state.statistics.civilians_lost += 1,
+                        _ => (),
+                    }
+                    entity.behaviour = Behaviour::Dead;
+                    sounds.push(Sound::ZombieDeath);
+                }
                 p.velocity = Vector2::zero();
-                sounds.push(Sound::ZombieDeath);
             }
         }
     }
 
+    // Cops who died or turned this tick leave their control groups
+    for group in &mut state.control_groups {
+        let entities = &state.entities;
+        group.retain(|&i| match entities[i].behaviour { Behaviour::Cop { .. } => true, _ => false });
+    }
+
+    // Barricades finished or knocked down this tick change the way around
+    if state.obstacles_changed {
+        state.rebuild_visibility_graph();
+    }
+
+    state.tick += 1;
+    state.time += args.dt;
+
     sounds
 }
 
 fn handle_collision(
     args: &UpdateArgs,
     entities: &mut Vec<Entity>,
+    rng: &mut XorShiftRng,
+    statistics: &mut Statistics,
+    decals: &mut Vec<Decal>,
     i: usize,
     j: usize,
     delta: &Vector2,
     delta_length_squared: f64,
     sounds: &mut Vec<Sound>) {
 
-    // Spread the infection from zombies to others
-    match (&entities[i].behaviour, &entities[j].behaviour) {
-        (Behaviour::Human, Behaviour::Zombie { .. }) | (Behaviour::Cop { .. }, Behaviour::Zombie { .. }) => {
-            entities[i].behaviour = Behaviour::Zombie { state: ZombieState::Roaming };
-            sounds.push(Sound::PersonInfected);
-        }
-        (Behaviour::Zombie { .. }, Behaviour::Human) | (Behaviour::Zombie { .. }, Behaviour::Cop { .. }) => {
-            entities[j].behaviour = Behaviour::Zombie { state: ZombieState::Roaming };
-            sounds.push(Sound::PersonInfected);
-        }
-        _ => ()
+    // Zombies bite the living, unless they are too dazed to
+    let victim = match (&entities[i].behaviour, &entities[j].behaviour) {
+        (_, Behaviour::Zombie { state: ZombieState::Stunned { .. } }) |
+        (Behaviour::Zombie { state: ZombieState::Stunned { .. } }, _) => None,
+        (Behaviour::Human { .. }, Behaviour::Zombie { .. }) | (Behaviour::Cop { .. }, Behaviour::Zombie { .. }) => Some(i),
+        (Behaviour::Zombie { .. }, Behaviour::Human { .. }) | (Behaviour::Zombie { .. }, Behaviour::Cop { .. }) => Some(j),
+        _ => None
+    };
+    if let Some(victim) = victim {
+        bite(args, &mut entities[victim], rng, statistics, decals, sounds);
     }
 
     // Force entities apart that are overlapping
@@ -209,6 +333,96 @@ fn handle_collision(
     entities[j].velocity += velocity_change;
 }
 
+// Wear down the health of someone being bitten. If they die of their wounds they turn
+// straight away, and if they fail to resist the infection they turn after a while
+fn bite(
+    args: &UpdateArgs,
+    victim: &mut Entity,
+    rng: &mut XorShiftRng,
+    statistics: &mut Statistics,
+    decals: &mut Vec<Decal>,
+    sounds: &mut Vec<Sound>) {
+
+    victim.health -= ZOMBIE_BITE_DAMAGE_PER_SECOND * args.dt;
+
+    if victim.health <= 0.0 {
+        turn_into_zombie(victim, statistics, decals, sounds);
+    } else if victim.incubation_time_remaining.is_none() &&
+        rng.gen_range(0.0, 1.0) < BITE_INFECTION_CHANCE_PER_SECOND * args.dt {
+        victim.incubation_time_remaining = Some(rng.gen_range(INCUBATION_TIME_MIN, INCUBATION_TIME_MAX));
+        decals.push(Decal { kind: DecalKind::BloodSplat, position: victim.position, angle: victim.facing_angle, radius: 0.4 });
+    }
+}
+
+fn turn_into_zombie(entity: &mut Entity, statistics: &mut Statistics, decals: &mut Vec<Decal>, sounds: &mut Vec<Sound>) {
+    if let Behaviour::Human { .. } = entity.behaviour {
+        statistics.civilians_lost += 1;
+    }
+    decals.push(Decal { kind: DecalKind::BloodSplat, position: entity.position, angle: entity.facing_angle, radius: 0.6 });
+    entity.behaviour = Behaviour::Zombie { state: ZombieState::Roaming };
+    entity.health = ENTITY_MAX_HEALTH;
+    entity.incubation_time_remaining = None;
+    sounds.push(Sound::PersonInfected);
+}
+
+// Damage done by a bullet moving at speed along the segment to the entity at target_pos.
+// Shots through the middle of the entity hit something vital, and glancing shots only
+// hit a limb
+fn bullet_damage(speed: Scalar, segment: &Segment2, target_pos: Vector2) -> Scalar {
+    let direction = (segment.p2 - segment.p1).normalize();
+    let offset = direction.cross(target_pos - segment.p1).abs() / ENTITY_RADIUS;
+
+    let location_multiplier = if offset < BULLET_VITAL_HIT_OFFSET {
+        BULLET_VITAL_DAMAGE_MULTIPLIER
+    } else if offset < BULLET_LIMB_HIT_OFFSET {
+        1.0
+    } else {
+        BULLET_LIMB_DAMAGE_MULTIPLIER
+    };
+
+    BULLET_DAMAGE * (speed / BULLET_SPEED) * location_multiplier
+}
+
+// Hit a zombie with a baton, knocking it back along the direction of the swing. The
+// blow may kill or stun it. Returns whether it killed it
+fn swing_baton(target: &mut Entity, direction: Vector2, rng: &mut XorShiftRng, sounds: &mut Vec<Sound>) -> bool {
+    target.velocity += BATON_KNOCKBACK_SPEED * direction.normalize();
+
+    if rng.gen_range(0.0, 1.0) < BATON_KILL_CHANCE {
+        target.behaviour = Behaviour::Dead;
+        sounds.push(Sound::ZombieDeath);
+        true
+    } else {
+        if rng.gen_range(0.0, 1.0) < BATON_STUN_CHANCE {
+            target.behaviour = Behaviour::Zombie {
+                state: ZombieState::Stunned { stun_time_remaining: BATON_STUN_TIME }
+            };
+        }
+        false
+    }
+}
+
+// Check whether the circle overlaps the polygon. If it does, say whether the
+// circle's centre is inside the polygon
+fn polygon_overlap(polygon: &Polygon, circle: &Circle) -> Option<bool> {
+    if polygon.contains_point(circle.center) {
+        return Some(true);
+    }
+
+    // Check if one of the polygon's sides intersects the circle
+    for k in 0..polygon.num_sides() {
+        let segment = Segment2 {
+            p1: polygon.get(k),
+            p2: polygon.get((k + 1) % polygon.num_sides())
+        };
+
+        if segment_circle_has_intersection(&segment, &circle) {
+            return Some(false);
+        }
+    }
+    None
+}
+
 fn handle_building_collision(
     args: &UpdateArgs,
     entity: &mut Entity,
@@ -247,29 +461,18 @@ fn handle_building_collision(
     }
 }
 
-fn can_see(
-    buildings: &Vec<Polygon>,
-    from: Vector2,
-    to: Vector2) -> bool {
-
-    for building in buildings {
-        let num_intersects = building.num_intersects(from, to);
-        if num_intersects > 0 {
-            return false;
-        }
-    };
-    return true;
-}
-
 fn update_cop(
     args: &UpdateArgs,
     sim_state: &mut State,
+    entity_grid: &SpatialGrid,
+    building_grid: &SpatialGrid,
     index: usize,
-    sounds: &mut Vec<Sound>){
+    sounds: &mut Vec<Sound>,
+    noises: &mut Vec<Noise>){
 
     let entities = &mut sim_state.entities;
     let buildings = &sim_state.buildings;
-    let building_outlines = &sim_state.building_outlines;
+    let visibility_graph = &sim_state.visibility_graph;
 
     enum StateChange {
         // Exit the state you're in
@@ -286,14 +489,35 @@ fn update_cop(
     unsafe {
     let entity = &mut entities[index] as *mut Entity;
     match &mut (*entity).behaviour {
-        Behaviour::Cop { rounds_in_magazine, state_stack } => {
+        Behaviour::Cop { rounds_in_magazine, magazine_capacity, rounds_in_reserve, state_stack, queued_orders } => {
+            let out_of_ammo = *rounds_in_magazine <= 0 && *rounds_in_reserve == Some(0);
+
+            // A zombie within arm's reach has to be fought off before doing anything else
+            let zombie_in_reach = match state_stack.last() {
+                Some(CopState::Melee { .. }) => None,
+                _ => closest_zombie(entities[index].position, entities, entity_grid, COP_MELEE_REACH),
+            };
+
             let state_change = match state_stack.last() {
+                _ if zombie_in_reach.is_some() => {
+                    StateChange::Enter(CopState::Melee {
+                        target_index: zombie_in_reach.unwrap(),
+                        swing_cooldown_remaining: COP_MELEE_WINDUP_TIME
+                    })
+                }
                 Some(CopState::AttackingZombie { target_index, path: _ }) => {
 
                     if let Behaviour::Dead = entities[*target_index].behaviour {
                         // Target is dead, stop attacking
                         StateChange::Exit
                     }
+                    else if out_of_ammo {
+                        // Out of ammo with nothing left to reload, carry on the attack with a baton
+                        StateChange::Update(CopState::Melee {
+                            target_index: *target_index,
+                            swing_cooldown_remaining: COP_MELEE_WINDUP_TIME
+                        })
+                    }
                     else if *rounds_in_magazine <= 0 {
                         // Out of ammo, need to reload before we can attack
                         StateChange::Enter(
@@ -304,6 +528,7 @@ fn update_cop(
                     }
                     else if can_see(
                         &sim_state.buildings,
+                        building_grid,
                         (*entity).position,
                         entities[*target_index].position) {
                         // Can see the target, take aim
@@ -315,7 +540,7 @@ fn update_cop(
                         )
                     }
                     else {
-                        match find_path(entities[index].position, entities[*target_index].position, buildings, building_outlines) {
+                        match find_path(entities[index].position, entities[*target_index].position, visibility_graph) {
                             None => {
                                 // No path to zombie possible, end chase
                                 StateChange::Exit
@@ -347,6 +572,7 @@ fn update_cop(
 
                     // Stop aiming if we can no longer see the target
                     else if !can_see(buildings,
+                                building_grid,
                                 entities[index].position,
                                 entities[*target_index].position) {
                         StateChange::Exit
@@ -380,7 +606,8 @@ fn update_cop(
                                 Projectile {
                                     position: spawn_pos,
                                     velocity: BULLET_SPEED * delta_normal,
-                                    kind: ProjectileKind::Bullet
+                                    kind: ProjectileKind::Bullet,
+                                    shooter: Some(index),
                                 });
 
                             sim_state.projectiles.push(
@@ -388,19 +615,22 @@ fn update_cop(
                                     position: spawn_pos,
                                     // Casing ejects from the right of the weapon
                                     velocity: CASING_SPEED * delta_normal.right(),
-                                    kind: ProjectileKind::Casing
+                                    kind: ProjectileKind::Casing,
+                                    shooter: None,
                                 });
+                            sim_state.statistics.shots_fired += 1;
 
                             sounds.push(Sound::Gunshot);
+                            noises.push(Noise { position: spawn_pos, loudness: GUNSHOT_LOUDNESS });
                             StateChange::Exit
                         }
                     }
 
                 }
-                Some(CopState::Moving { waypoint, mode, path: _ }) => {
+                Some(CopState::Moving { waypoint, mode, path }) => {
                     match mode {
                         MoveMode::Moving => {
-                            match find_path(entities[index].position, *waypoint, buildings, building_outlines) {
+                            match follow_path(entities[index].position, *waypoint, path, visibility_graph) {
                                 None => {
                                     StateChange::Exit
                                 },
@@ -429,6 +659,102 @@ fn update_cop(
                         }
                     }
                 }
+                Some(CopState::Melee { target_index, swing_cooldown_remaining }) => {
+                    let my_pos = entities[index].position;
+                    let target_pos = entities[*target_index].position;
+                    let delta = target_pos - my_pos;
+
+                    match entities[*target_index].behaviour {
+                        Behaviour::Zombie { .. } => {
+                            if delta.length() > COP_MELEE_REACH {
+                                // With ammo, the gun is the better option once the zombie is out of
+                                // reach. Without, close in on any zombie still in sight
+                                if !out_of_ammo ||
+                                    delta.length_squared() > COP_SIGHT_RADIUS_SQUARE ||
+                                    !can_see(buildings, building_grid, my_pos, target_pos) {
+                                    StateChange::Exit
+                                } else {
+                                    entities[index].accelerate_along_vector(delta, args.dt, COP_MOVEMENT_FORCE);
+                                    StateChange::Update(CopState::Melee {
+                                        target_index: *target_index,
+                                        swing_cooldown_remaining: (*swing_cooldown_remaining - args.dt).max(0.0)
+                                    })
+                                }
+                            } else {
+                                entities[index].look_along_vector(delta, args.dt);
+
+                                if *swing_cooldown_remaining > args.dt {
+                                    StateChange::Update(CopState::Melee {
+                                        target_index: *target_index,
+                                        swing_cooldown_remaining: *swing_cooldown_remaining - args.dt
+                                    })
+                                } else {
+                                    if swing_baton(&mut entities[*target_index], delta, &mut sim_state.rng, sounds) {
+                                        sim_state.statistics.record_kill(index);
+                                    }
+                                    StateChange::Update(CopState::Melee {
+                                        target_index: *target_index,
+                                        swing_cooldown_remaining: COP_MELEE_SWING_COOLDOWN
+                                    })
+                                }
+                            }
+                        }
+                        // The target is dead, stop fighting
+                        _ => StateChange::Exit
+                    }
+                }
+                Some(CopState::Building { barricade_index, path }) => {
+                    let my_pos = entities[index].position;
+                    let barricade = &mut sim_state.barricades[*barricade_index];
+
+                    if barricade.is_built() {
+                        StateChange::Exit
+                    } else if barricade.segment().dist_squared(my_pos) < COP_BUILD_REACH * COP_BUILD_REACH {
+                        entities[index].look_at_point(barricade.segment().nearest_point_to_point(my_pos), args.dt);
+
+                        barricade.build_time_remaining -= args.dt;
+                        if barricade.is_built() {
+                            sim_state.obstacles_changed = true;
+                        }
+                        StateChange::Continue
+                    } else {
+                        match follow_path(my_pos, barricade.work_position(my_pos), path, visibility_graph) {
+                            None => StateChange::Exit,
+                            Some(path) => {
+                                let delta = path.edges[0].end.pos - my_pos;
+                                entities[index].accelerate_along_vector(delta, args.dt, COP_MOVEMENT_FORCE);
+                                StateChange::Update(CopState::Building {
+                                    barricade_index: *barricade_index,
+                                    path: Some(path)
+                                })
+                            }
+                        }
+                    }
+                }
+                Some(CopState::Garrisoning { building_index, path }) => {
+                    let my_pos = entities[index].position;
+                    let door = sim_state.shelters[*building_index].door;
+
+                    if (*entity).sheltering_in.is_some() || !sim_state.shelters[*building_index].has_room() {
+                        // Already inside, or there's no way in
+                        StateChange::Exit
+                    } else if (door - my_pos).length() < SHELTER_ENTRY_DISTANCE {
+                        enter_shelter(&mut sim_state.shelters, entities, *building_index, index);
+                        StateChange::Exit
+                    } else {
+                        match follow_path(my_pos, door, path, visibility_graph) {
+                            None => StateChange::Exit,
+                            Some(path) => {
+                                let delta = path.edges[0].end.pos - my_pos;
+                                entities[index].accelerate_along_vector(delta, args.dt, COP_MOVEMENT_FORCE);
+                                StateChange::Update(CopState::Garrisoning {
+                                    building_index: *building_index,
+                                    path: Some(path)
+                                })
+                            }
+                        }
+                    }
+                }
                 Some(CopState::Reloading { reload_time_remaining }) => {
                     let half_reload_time = 0.5 * COP_RELOAD_COOLDOWN;
                     let new_reload_time_remaining = reload_time_remaining - args.dt;
@@ -444,61 +770,86 @@ fn update_cop(
                             reload_time_remaining: new_reload_time_remaining
                         })
                     } else {
-                        // Finished reloading: replenish rounds and return to the previous state
-                        *rounds_in_magazine = COP_MAGAZINE_CAPACITY;
+                        // Finished reloading: replenish rounds from the reserve and return to the previous state
+                        let rounds_loaded = match rounds_in_reserve {
+                            Some(reserve) => {
+                                let rounds = (*magazine_capacity - *rounds_in_magazine).min(*reserve).max(0);
+                                *reserve -= rounds;
+                                rounds
+                            }
+                            None => *magazine_capacity - *rounds_in_magazine,
+                        };
+                        *rounds_in_magazine += rounds_loaded;
                         StateChange::Exit
                     }
                 }
-                None => {
-                    // Reload if you don't have ammo
-                    if *rounds_in_magazine <= 0 {
-                        StateChange::Enter(CopState::Reloading { reload_time_remaining: COP_RELOAD_COOLDOWN })
-                    }
-                    // Look for target if you do have ammo
-                    else {
-                        let my_pos = entities[index].position;
-
-                        let mut min_index = 0;
-                        let mut min_distance_sqr = INFINITY;
-
-                        for i in 0..entities.len() {
-                            match entities[i].behaviour {
-
-                                // Target zombies
-                                Behaviour::Zombie { .. } => {
-                                    let delta = entities[i].position - my_pos;
-                                    let distance_sqr = delta.length_squared();
-                                    if distance_sqr < min_distance_sqr {
-
-                                        // make sure we can actually see the target
-                                        if !can_see(buildings,
-                                                    entities[index].position,
-                                                    entities[i].position) {
-                                            continue;
-                                        }
-
-                                        min_index = i;
-                                        min_distance_sqr = distance_sqr;
-                                    }
+                // Carry on with the next order the player queued up
+                None if !queued_orders.is_empty() => {
+                    StateChange::Enter(queued_orders.remove(0))
+                }
+                Some(CopState::Patrolling { waypoints, next, path }) => {
+                    let my_pos = entities[index].position;
+                    let waypoint = waypoints[*next];
+
+                    match engage_zombies(my_pos, *rounds_in_magazine, out_of_ammo, false, entities, buildings,
+                                         entity_grid, building_grid, &mut sim_state.rng) {
+                        Some(engagement) => StateChange::Enter(engagement),
+                        None if (waypoint - my_pos).length_squared() < COP_MIN_DISTANCE_FROM_WAYPOINT_SQUARED => {
+                            // Made it, on to the next one, going back to the first after the last
+                            StateChange::Update(CopState::Patrolling {
+                                waypoints: waypoints.clone(),
+                                next: (*next + 1) % waypoints.len(),
+                                path: None
+                            })
+                        }
+                        None => {
+                            match follow_path(my_pos, waypoint, path, visibility_graph) {
+                                None => StateChange::Exit,
+                                Some(path) => {
+                                    let delta = path.edges[0].end.pos - my_pos;
+                                    entities[index].accelerate_along_vector(delta, args.dt, COP_MOVEMENT_FORCE);
+                                    StateChange::Update(CopState::Patrolling {
+                                        waypoints: waypoints.clone(),
+                                        next: *next,
+                                        path: Some(path)
+                                    })
                                 }
-
-                                // Skip everything else
-                                _ => ()
                             }
                         }
-
-                        if min_distance_sqr < INFINITY {
-                            let aim_time_distribution = Exp::new(COP_AIM_TIME_MEAN);
-                            StateChange::Enter(CopState::Aiming {
-                                aim_time_remaining: aim_time_distribution.sample(&mut sim_state.rng),
-                                target_index: min_index,
-                            })
-                        } else {
-                            // Remain in idle state
-                            StateChange::Continue
+                    }
+                }
+                Some(CopState::Guarding { anchor, path }) => {
+                    let my_pos = entities[index].position;
+
+                    match engage_zombies(my_pos, *rounds_in_magazine, out_of_ammo, false, entities, buildings,
+                                         entity_grid, building_grid, &mut sim_state.rng) {
+                        Some(engagement) => StateChange::Enter(engagement),
+                        // Hold position
+                        None if (*anchor - my_pos).length_squared() < COP_MIN_DISTANCE_FROM_WAYPOINT_SQUARED => {
+                            StateChange::Update(CopState::Guarding { anchor: *anchor, path: None })
+                        }
+                        // Head back after being drawn away. Stay put if there's no way back
+                        None => {
+                            match follow_path(my_pos, *anchor, path, visibility_graph) {
+                                None => StateChange::Continue,
+                                Some(path) => {
+                                    let delta = path.edges[0].end.pos - my_pos;
+                                    entities[index].accelerate_along_vector(delta, args.dt, COP_MOVEMENT_FORCE);
+                                    StateChange::Update(CopState::Guarding { anchor: *anchor, path: Some(path) })
+                                }
+                            }
                         }
                     }
                 }
+                None => {
+                    // Idle cops take aim at any zombie in sight, however far off
+                    match engage_zombies(entities[index].position, *rounds_in_magazine, out_of_ammo, true, entities, buildings,
+                                         entity_grid, building_grid, &mut sim_state.rng) {
+                        Some(engagement) => StateChange::Enter(engagement),
+                        // Remain in idle state
+                        None => StateChange::Continue,
+                    }
+                }
             };
 
             match state_change {
@@ -516,9 +867,126 @@ fn update_cop(
     }
 }
 
+// Keep following a planned path, so that it doesn't have to be planned again every tick.
+// A new path is only planned if there isn't one, or if we've been pushed out of sight
+// of the next corner
+fn follow_path(
+    position: Vector2,
+    destination: Vector2,
+    path: &Option<Path>,
+    visibility_graph: &VisibilityGraph) -> Option<Path> {
+
+    let mut path = match path {
+        Some(path) if visibility_graph.has_clear_path(position, path.edges[0].end.pos) => path.clone(),
+        _ => find_path(position, destination, visibility_graph)?,
+    };
+
+    // Head straight for the furthest corner in sight, rather than going via the ones before it
+    let mut next = 0;
+    while next + 1 < path.edges.len() &&
+        visibility_graph.has_clear_path(position, path.edges[next + 1].end.pos) {
+        next += 1;
+    }
+    path.start_from(position, next);
+
+    Some(path)
+}
+
+// Deal with the zombies around a cop who isn't busy with anything else: reload an empty
+// magazine, then shoot the closest zombie in sight, or charge it with a baton when out
+// of ammo and it is close. Only zombies within COP_SIGHT_RADIUS are shot at, unless
+// unlimited_range is set. None if there is nothing to do
+fn engage_zombies(
+    my_pos: Vector2,
+    rounds_in_magazine: i64,
+    out_of_ammo: bool,
+    unlimited_range: bool,
+    entities: &Vec<Entity>,
+    buildings: &Vec<Polygon>,
+    entity_grid: &SpatialGrid,
+    building_grid: &SpatialGrid,
+    rng: &mut XorShiftRng) -> Option<CopState> {
+
+    // Reload if you don't have ammo
+    if rounds_in_magazine <= 0 && !out_of_ammo {
+        return Some(CopState::Reloading { reload_time_remaining: COP_RELOAD_COOLDOWN });
+    }
+
+    // Look for a target, to shoot if you have ammo or to fight hand to hand if not
+    let mut min_index = 0;
+    let mut min_distance_sqr = INFINITY;
+
+    for i in entity_grid.query(my_pos, COP_SIGHT_RADIUS) {
+        match entities[i].behaviour {
+
+            // Target zombies
+            Behaviour::Zombie { .. } => {
+                let delta = entities[i].position - my_pos;
+                let distance_sqr = delta.length_squared();
+                if distance_sqr < COP_SIGHT_RADIUS_SQUARE &&
+                    distance_sqr < min_distance_sqr {
+
+                    // make sure we can actually see the target
+                    if !can_see(buildings,
+                                building_grid,
+                                my_pos,
+                                entities[i].position) {
+                        continue;
+                    }
+
+                    min_index = i;
+                    min_distance_sqr = distance_sqr;
+                }
+            }
+
+            // Skip everything else
+            _ => ()
+        }
+    }
+
+    // Nothing in sight nearby, so look further afield. Check the closest zombies first,
+    // since most of those far away will be out of sight
+    if unlimited_range && !out_of_ammo && min_distance_sqr == INFINITY {
+        let mut distant: Vec<(Scalar, usize)> = entities.iter()
+            .enumerate()
+            .filter(|(_, entity)| match entity.behaviour { Behaviour::Zombie { .. } => true, _ => false })
+            .map(|(i, entity)| ((entity.position - my_pos).length_squared(), i))
+            .filter(|&(distance_sqr, _)| distance_sqr >= COP_SIGHT_RADIUS_SQUARE)
+            .collect();
+        distant.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        if let Some(&(distance_sqr, i)) = distant.iter()
+            .find(|&&(_, i)| can_see(buildings, building_grid, my_pos, entities[i].position)) {
+            min_index = i;
+            min_distance_sqr = distance_sqr;
+        }
+    }
+
+    if out_of_ammo {
+        if min_distance_sqr < COP_MELEE_CHARGE_RADIUS * COP_MELEE_CHARGE_RADIUS {
+            Some(CopState::Melee {
+                target_index: min_index,
+                swing_cooldown_remaining: COP_MELEE_WINDUP_TIME
+            })
+        } else {
+            None
+        }
+    } else if min_distance_sqr < INFINITY {
+        let aim_time_distribution = Exp::new(COP_AIM_TIME_MEAN);
+        Some(CopState::Aiming {
+            aim_time_remaining: aim_time_distribution.sample(rng),
+            target_index: min_index,
+        })
+    } else {
+        None
+    }
+}
+
 fn update_zombie(
     args: &UpdateArgs,
     sim_state: &mut State,
+    entity_grid: &SpatialGrid,
+    building_grid: &SpatialGrid,
     index: usize,
     behaviour: Behaviour) -> Behaviour {
 
@@ -530,6 +998,12 @@ fn update_zombie(
     match behaviour {
         Behaviour::Zombie { state } => {
             match state {
+                ZombieState::Chasing { target_index } if entities[target_index].sheltering_in.is_some() => {
+                    // The target got away into a building, so go after the door instead
+                    Behaviour::Zombie {
+                        state: ZombieState::AttackingDoor { building_index: entities[target_index].sheltering_in.unwrap() }
+                    }
+                }
                 ZombieState::Chasing { target_index } => {
                     let target_pos = entities[target_index].position;
                     let delta = target_pos - my_pos;
@@ -538,8 +1012,8 @@ fn update_zombie(
 
                     match entities[target_index].behaviour {
                         // If alive, check line of sight
-                        Behaviour::Cop { .. } | Behaviour::Human => {
-                            if delta.length_squared() < ZOMBIE_SIGHT_RADIUS_SQUARE && can_see(buildings,my_pos,target_pos) {
+                        Behaviour::Cop { .. } | Behaviour::Human { .. } => {
+                            if delta.length_squared() < ZOMBIE_SIGHT_RADIUS_SQUARE && can_see(buildings, building_grid, my_pos, target_pos) {
                                 // Continue chasing
                                 Behaviour::Zombie { state: ZombieState::Chasing { target_index } }
                             } else {
@@ -554,7 +1028,7 @@ fn update_zombie(
                     }
                 }
                 ZombieState::Moving { waypoint } => {
-                    match closest_human(my_pos, entities, buildings) {
+                    match closest_human(my_pos, entities, buildings, entity_grid, building_grid) {
                         // Continue moving
                         None => {
                             let delta = waypoint - my_pos;
@@ -574,10 +1048,51 @@ fn update_zombie(
                         }
                     }
                 }
+                ZombieState::Stunned { stun_time_remaining } => {
+                    if stun_time_remaining > args.dt {
+                        Behaviour::Zombie {
+                            state: ZombieState::Stunned { stun_time_remaining: stun_time_remaining - args.dt }
+                        }
+                    } else {
+                        Behaviour::Zombie { state: ZombieState::Roaming }
+                    }
+                }
+                ZombieState::AttackingDoor { building_index } => {
+                    let shelter = &mut sim_state.shelters[building_index];
+
+                    match closest_human(my_pos, entities, buildings, entity_grid, building_grid) {
+                        // Anyone out in the open is easier prey
+                        Some(i) => {
+                            let delta = entities[i].position - my_pos;
+                            entities[index].accelerate_along_vector(delta, args.dt, ZOMBIE_MOVEMENT_FORCE);
+                            Behaviour::Zombie { state: ZombieState::Chasing { target_index: i } }
+                        }
+                        None if shelter.is_breached() || shelter.occupants.is_empty() =>
+                            Behaviour::Zombie { state: ZombieState::Roaming },
+                        None => {
+                            let delta = shelter.door - my_pos;
+                            entities[index].look_along_vector(delta, args.dt);
+
+                            if delta.length() > ZOMBIE_DOOR_REACH {
+                                entities[index].accelerate_along_vector(delta, args.dt, ZOMBIE_MOVEMENT_FORCE);
+                            } else {
+                                shelter.door_health -= ZOMBIE_DOOR_DAMAGE_PER_SECOND * args.dt;
+                                if shelter.is_breached() {
+                                    breach_shelter(shelter, entities, &mut sim_state.rng);
+                                }
+                            }
+                            Behaviour::Zombie { state: ZombieState::AttackingDoor { building_index } }
+                        }
+                    }
+                }
                 ZombieState::Roaming => {
                     // Attempt to acquire a target
-                    match closest_human(my_pos, entities, buildings) {
-                        None => Behaviour::Zombie { state: ZombieState::Roaming },
+                    match closest_human(my_pos, entities, buildings, entity_grid, building_grid) {
+                        None => match occupied_shelter_nearby(my_pos, &sim_state.shelters, building_grid) {
+                            // Sniff out anyone hiding nearby
+                            Some(building_index) => Behaviour::Zombie { state: ZombieState::AttackingDoor { building_index } },
+                            None => Behaviour::Zombie { state: ZombieState::Roaming },
+                        },
                         Some(i) => {
                             let delta = entities[i].position - my_pos;
                             entities[index].accelerate_along_vector(delta, args.dt, ZOMBIE_MOVEMENT_FORCE);
@@ -591,18 +1106,54 @@ fn update_zombie(
     }
 }
 
+// Get the index of the closest zombie within the radius
+fn closest_zombie(
+    my_pos: Vector2,
+    entities: &Vec<Entity>,
+    entity_grid: &SpatialGrid,
+    radius: Scalar) -> Option<usize> {
+    let mut min_distance_sqr = radius * radius;
+    let mut closest_index: Option<usize> = None;
+
+    for i in entity_grid.query(my_pos, radius) {
+        if let Behaviour::Zombie { .. } = entities[i].behaviour {
+            let delta_squared = (my_pos - entities[i].position).length_squared();
+            if delta_squared < min_distance_sqr {
+                min_distance_sqr = delta_squared;
+                closest_index = Some(i);
+            }
+        }
+    }
+
+    closest_index
+}
+
+// Get the index of a building near enough for a zombie to smell the people hiding inside
+fn occupied_shelter_nearby(my_pos: Vector2, shelters: &Vec<Shelter>, building_grid: &SpatialGrid) -> Option<usize> {
+    building_grid.query(my_pos, ZOMBIE_SCENT_RADIUS).find(|&i| {
+        let shelter = &shelters[i];
+        !shelter.is_breached() && !shelter.occupants.is_empty() &&
+            (shelter.door - my_pos).length_squared() < ZOMBIE_SCENT_RADIUS * ZOMBIE_SCENT_RADIUS
+    })
+}
+
 // Get the index of the closest human in line of sight and sight radius
-fn closest_human(my_pos: Vector2, entities: &Vec<Entity>, buildings: &Vec<Polygon>) -> Option<usize> {
+fn closest_human(
+    my_pos: Vector2,
+    entities: &Vec<Entity>,
+    buildings: &Vec<Polygon>,
+    entity_grid: &SpatialGrid,
+    building_grid: &SpatialGrid) -> Option<usize> {
     let mut min_distance_sqr = INFINITY;
     let mut closest_index: Option<usize> = None;
 
-    for i in 0..entities.len() {
+    for i in entity_grid.query(my_pos, ZOMBIE_SIGHT_RADIUS) {
         match entities[i].behaviour {
-            Behaviour::Cop { .. } | Behaviour::Human => {
+            Behaviour::Cop { .. } | Behaviour::Human { .. } => {
                 let delta_squared = (my_pos - entities[i].position).length_squared();
                 if delta_squared < ZOMBIE_SIGHT_RADIUS_SQUARE &&
-                    can_see(buildings, my_pos, entities[i].position) &&
-                    delta_squared < min_distance_sqr {
+                    delta_squared < min_distance_sqr &&
+                    can_see(buildings, building_grid, my_pos, entities[i].position) {
 
                     min_distance_sqr = delta_squared;
                     closest_index = Some(i);
@@ -615,35 +1166,180 @@ fn closest_human(my_pos: Vector2, entities: &Vec<Entity>, buildings: &Vec<Polygo
     closest_index
 }
 
-fn simulate_human(args: &UpdateArgs, entities: &mut Vec<Entity>, buildings: &Vec<Polygon>, index: usize) {
+// Steer a civilian by weighing up several urges: keep some space from the people around
+// them, but stick with the crowd, run from zombies and toward cops, and keep clear of walls.
+// A civilian who sees a zombie also heads for the nearest safe zone
+fn simulate_human(
+    args: &UpdateArgs,
+    sim_state: &mut State,
+    entity_grid: &SpatialGrid,
+    building_grid: &SpatialGrid,
+    index: usize) {
+
+    let entities = &mut sim_state.entities;
+    let buildings = &sim_state.buildings;
     let my_pos = entities[index].position;
 
-    let mut min_delta = Vector2::zero();
-    let mut min_distance_sqr = INFINITY;
+    let mut separation = Vector2::zero();
+    let mut flee = Vector2::zero();
+    let mut crowd_offset = Vector2::zero();
+    let mut crowd_count = 0;
+    let mut nearest_cop_delta = None;
+    let mut nearest_cop_distance_sqr = INFINITY;
+
+    for i in entity_grid.query(my_pos, HUMAN_SIGHT_RADIUS) {
+        let delta = entities[i].position - my_pos;
+        let distance_sqr = delta.length_squared();
+        if i == index || distance_sqr == 0.0 || distance_sqr >= HUMAN_SIGHT_RADIUS_SQUARE {
+            continue;
+        }
 
-    for i in 0..entities.len() {
         match entities[i].behaviour {
-
-            // Run from zombies
+            // Run from every zombie in sight, the closest ones most of all
             Behaviour::Zombie { .. } => {
-                let delta = entities[i].position - my_pos;
-                let distance_sqr = delta.length_squared();
-                if distance_sqr < HUMAN_SIGHT_RADIUS_SQUARE &&
-                    can_see(buildings, my_pos, entities[i].position) &&
-                    distance_sqr < min_distance_sqr {
-
-                    min_delta = delta;
-                    min_distance_sqr = distance_sqr;
+                if can_see(buildings, building_grid, my_pos, entities[i].position) {
+                    flee -= delta / distance_sqr;
+                }
+            }
+            Behaviour::Human { .. } => {
+                if distance_sqr < HUMAN_SEPARATION_RADIUS * HUMAN_SEPARATION_RADIUS {
+                    separation -= delta / distance_sqr;
+                }
+                if distance_sqr < HUMAN_COHESION_RADIUS * HUMAN_COHESION_RADIUS {
+                    crowd_offset += delta;
+                    crowd_count += 1;
                 }
             }
+            Behaviour::Cop { .. } => {
+                if distance_sqr < HUMAN_SEPARATION_RADIUS * HUMAN_SEPARATION_RADIUS {
+                    separation -= delta / distance_sqr;
+                }
+                if distance_sqr < nearest_cop_distance_sqr {
+                    nearest_cop_delta = Some(delta);
+                    nearest_cop_distance_sqr = distance_sqr;
+                }
+            }
+            Behaviour::Dead => ()
+        }
+    }
 
-            // Skip everything else
-            _ => ()
+    let mut steering = HUMAN_SEPARATION_WEIGHT * separation +
+        HUMAN_WALL_AVOIDANCE_WEIGHT * wall_avoidance(my_pos, buildings, building_grid);
+
+    let old_path = match &entities[index].behaviour {
+        Behaviour::Human { safe_zone_path } => safe_zone_path.clone(),
+        _ => panic!("Entity at index should be a civilian!")
+    };
+    let mut safe_zone_path = None;
+
+    // Calm civilians stay put, apart from keeping their distance
+    if flee != Vector2::zero() {
+        steering += HUMAN_FLEE_WEIGHT * flee.normalize();
+
+        if crowd_count > 0 {
+            steering += HUMAN_COHESION_WEIGHT * (crowd_offset / crowd_count as Scalar).normalize();
+        }
+
+        if let Some(delta) = nearest_cop_delta {
+            if can_see(buildings, building_grid, my_pos, my_pos + delta) {
+                steering += HUMAN_SEEK_COPS_WEIGHT * delta.normalize();
+            }
+        }
+
+        // A building close by is the quickest way to safety, otherwise make for a safe zone:
+        // a checkpoint, or a plaza where there are none
+        let safe_zones = if sim_state.checkpoints.is_empty() { &sim_state.plazas } else { &sim_state.checkpoints };
+        if let Some(building_index) = nearest_open_shelter(my_pos, &sim_state.shelters, buildings, building_grid) {
+            let delta = sim_state.shelters[building_index].door - my_pos;
+            if delta.length() < SHELTER_ENTRY_DISTANCE {
+                enter_shelter(&mut sim_state.shelters, entities, building_index, index);
+                entities[index].behaviour = Behaviour::Human { safe_zone_path: None };
+                return;
+            }
+            steering += HUMAN_SHELTER_WEIGHT * delta.normalize();
+        }
+        else if let Some(destination) = nearest_safe_zone(my_pos, safe_zones) {
+            // Keep the old route unless the nearest safe zone has changed
+            let old_path = old_path.filter(|path| path.edges.last().map(|edge| edge.end.pos) == Some(destination));
+            safe_zone_path = follow_path(my_pos, destination, &old_path, &sim_state.visibility_graph);
+
+            if let Some(path) = &safe_zone_path {
+                steering += HUMAN_SAFE_ZONE_WEIGHT * (path.edges[0].end.pos - my_pos).normalize();
+            }
         }
     }
 
-    if min_distance_sqr < INFINITY {
-        // Accelerate away from the nearest zombie
-        entities[index].accelerate_along_vector(-min_delta, args.dt, CIVILIAN_MOVEMENT_FORCE);
+    // Weak urges only make for a gentle push
+    let urgency = steering.length().min(1.0);
+    if urgency > 0.0 {
+        entities[index].accelerate_along_vector(steering, args.dt, urgency * CIVILIAN_MOVEMENT_FORCE);
     }
+
+    entities[index].behaviour = Behaviour::Human { safe_zone_path };
+}
+
+// Push away from nearby walls, harder the closer they are
+fn wall_avoidance(my_pos: Vector2, buildings: &Vec<Polygon>, building_grid: &SpatialGrid) -> Vector2 {
+    // The grid lists a building once for every cell it overlaps
+    let mut candidates: Vec<usize> = building_grid.query(my_pos, HUMAN_WALL_AVOIDANCE_DISTANCE).collect();
+    candidates.sort();
+    candidates.dedup();
+
+    let mut avoidance = Vector2::zero();
+    for i in candidates {
+        let building = &buildings[i];
+        for j in 0..building.num_sides() {
+            let side = Segment2 { p1: building.get(j), p2: building.get((j + 1) % building.num_sides()) };
+            let away = my_pos - side.nearest_point_to_point(my_pos);
+            let distance = away.length();
+
+            if distance > 0.0 && distance < HUMAN_WALL_AVOIDANCE_DISTANCE {
+                avoidance += (1.0 - distance / HUMAN_WALL_AVOIDANCE_DISTANCE) * (away / distance);
+            }
+        }
+    }
+    avoidance
+}
+
+// Get the index of the closest building in sight with room inside and its door intact
+fn nearest_open_shelter(
+    my_pos: Vector2,
+    shelters: &Vec<Shelter>,
+    buildings: &Vec<Polygon>,
+    building_grid: &SpatialGrid) -> Option<usize> {
+
+    // The grid lists a building once for every cell it overlaps
+    let mut candidates: Vec<usize> = building_grid.query(my_pos, HUMAN_SHELTER_SEEK_RADIUS).collect();
+    candidates.sort();
+    candidates.dedup();
+
+    let mut min_distance_sqr = HUMAN_SHELTER_SEEK_RADIUS * HUMAN_SHELTER_SEEK_RADIUS;
+    let mut closest_index = None;
+    for i in candidates {
+        let distance_sqr = (shelters[i].door - my_pos).length_squared();
+        if shelters[i].has_room() &&
+            distance_sqr < min_distance_sqr &&
+            can_see(buildings, building_grid, my_pos, shelters[i].door) {
+
+            min_distance_sqr = distance_sqr;
+            closest_index = Some(i);
+        }
+    }
+    closest_index
+}
+
+// The middle of the closest safe zone, unless already inside one
+fn nearest_safe_zone(my_pos: Vector2, safe_zones: &Vec<Polygon>) -> Option<Vector2> {
+    if safe_zones.iter().any(|zone| zone.contains_point(my_pos)) {
+        return None;
+    }
+
+    safe_zones.iter()
+        .map(|zone| {
+            let (min, max) = zone.bounding_box();
+            0.5 * (min + max)
+        })
+        .min_by(|a, b| (*a - my_pos).length_squared()
+            .partial_cmp(&(*b - my_pos).length_squared())
+            .unwrap_or(std::cmp::Ordering::Equal))
 }